@@ -0,0 +1,108 @@
+//!
+//! Compares the trigger index of [matcher::Matcher] with the loop that was used before, which
+//! lowercased the message once per trigger of every response.
+//!
+//! Run with `cargo bench`.
+//!
+
+// The unit tests of the modules are not run by the benchmark harness
+#[path = "../src/matcher.rs"]
+#[allow(dead_code, unused_imports)]
+mod matcher;
+#[path = "../src/normalize.rs"]
+#[allow(dead_code, unused_imports)]
+mod normalize;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use matcher::{Matcher, Searchable};
+use normalize::{Normalizer, Step};
+
+/// Triggers that are normalized before they are compared
+struct Normalized(Vec<String>);
+
+impl Searchable for Normalized {
+    fn triggers(&self) -> Vec<String> {
+        self.0.clone()
+    }
+
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::new(&[Step::Nfkc, Step::Diacritics, Step::Repeated])
+    }
+}
+
+/// Triggers that are also found with typos
+struct Fuzzy(Vec<String>);
+
+impl Searchable for Fuzzy {
+    fn triggers(&self) -> Vec<String> {
+        self.0.clone()
+    }
+
+    fn max_distance(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+
+/// Creates `count` responses with two triggers each, similar to a large Känguru config.
+fn responses(count: usize) -> Vec<Vec<String>> {
+    (0..count)
+        .map(|idx| {
+            vec![
+                format!("schnapspraline {}", idx),
+                format!("box club {}", idx),
+            ]
+        })
+        .collect()
+}
+
+/// The loop that was used in `reply_to` before the trigger index was introduced.
+fn find_loop<'a>(responses: &'a [Vec<String>], message: &str) -> Option<&'a Vec<String>> {
+    for response in responses {
+        for trigger in response {
+            if message.to_lowercase().contains(&trigger.to_lowercase()) {
+                return Some(response);
+            }
+        }
+    }
+    None
+}
+
+fn bench_matcher(c: &mut Criterion) {
+    let message = "Das Känguru sagt: Die erste Regel des Boxclubs lautet, \
+        ihr redet nicht über den Boxclub! Ich mag Schnapspralinen.";
+
+    let mut group = c.benchmark_group("find trigger");
+    for count in [10, 100, 600] {
+        let responses = responses(count);
+        let matcher = Matcher::new(responses.clone());
+        let normalized = Matcher::new(
+            responses
+                .iter()
+                .map(|triggers| Normalized(triggers.clone()))
+                .collect(),
+        );
+        let fuzzy = Matcher::new(
+            responses
+                .iter()
+                .map(|triggers| Fuzzy(triggers.clone()))
+                .collect(),
+        );
+
+        group.bench_with_input(BenchmarkId::new("loop", count), &count, |b, _| {
+            b.iter(|| find_loop(black_box(&responses), black_box(message)))
+        });
+        group.bench_with_input(BenchmarkId::new("matcher", count), &count, |b, _| {
+            b.iter(|| matcher.find_where(black_box(message), |_| true))
+        });
+        group.bench_with_input(BenchmarkId::new("normalized", count), &count, |b, _| {
+            b.iter(|| normalized.find_where(black_box(message), |_| true))
+        });
+        group.bench_with_input(BenchmarkId::new("fuzzy", count), &count, |b, _| {
+            b.iter(|| fuzzy.find_where(black_box(message), |_| true))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_matcher);
+criterion_main!(benches);
@@ -4,13 +4,10 @@ fn main() {
     // Get the git hash of the current project.
     let git_hash = {
         match Command::new("git")
-            .args(&["rev-parse", "--short", "HEAD"])
+            .args(["rev-parse", "--short", "HEAD"])
             .output()
         {
-            Ok(output) => match String::from_utf8(output.stdout) {
-                Ok(s) => s,
-                Err(_) => String::new(),
-            },
+            Ok(output) => String::from_utf8(output.stdout).unwrap_or_default(),
             Err(_) => String::new(),
         }
     };
@@ -1,21 +1,43 @@
-use crate::regex;
+pub mod converter;
+pub mod format;
+pub mod include;
+pub mod migrate;
+pub mod schedule;
+pub mod scheduled;
+pub mod secrets;
+pub mod validate;
+
+use crate::config::{
+    converter::Converter, format::Format, schedule::Schedule, scheduled::ScheduledPost,
+    secrets::Secret,
+};
+use crate::matcher::{Match, Matcher, Searchable};
+use crate::normalize::{Normalizer, Step};
+use crate::quotes::{self, Quote};
+use chrono_tz::Tz;
 use log::{debug, error, info, trace, warn};
 use once_cell::sync::Lazy;
+use rand::Rng;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::Deserialize;
 use std::{
-    env,
+    cmp::Reverse,
+    collections::BTreeMap,
+    env, fmt,
     fs::{self, File},
     io::Write,
     path::Path,
     process::exit,
-    sync::Mutex,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 use toml::value;
 
 /// The response that is injected into a panic, if the config file was configured falsely
 const PANIC_RESPONSE: &str = "Please create a config file yourself or try setting the environment CONFIG_FILE to valid file location!";
 
-/// The versions of the config this program is compatible with
+/// The versions of the config this program is compatible with.
+/// Older versions are upgraded by the [migrate] module.
 const COMPATIBLE_VERSIONS: [(u32, u32); 2] = [(0, 2), (0, 3)];
 
 ///
@@ -36,43 +58,428 @@ const COMPATIBLE_VERSIONS: [(u32, u32); 2] = [(0, 2), (0, 3)];
 ///
 pub static CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| Mutex::new(Config::new()));
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 /// The default configuration struct that holds the global configuration structure
 pub struct Config {
     /// The version this config file was created with
     pub version: String,
+    /// Users of a guild, who are never replied to by any bot
+    #[serde(default)]
+    pub blocklist: Vec<Blocklist>,
     /// Holds configuration for the Autokommentator bot
     pub autokommentator: Autokommentator,
     /// Holds configuration for the Känguru Knecht bot
     pub kaenguru: Kaenguru,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
+/// Users of a guild, who are never replied to
+pub struct Blocklist {
+    /// The ID of the guild
+    pub guild: u64,
+    /// The IDs of the users
+    pub users: Vec<u64>,
+}
+
+#[derive(Deserialize, JsonSchema)]
 /// Structures the data used by the Känguru Knecht bot
 pub struct Kaenguru {
     /// The token that is used to log into discord
-    pub token: Option<String>,
+    #[schemars(with = "Option<String>")]
+    pub token: Option<Secret>,
+    /// A file that contains the token, e.g. a docker secret.
+    /// Only used, if `token` is not set.
+    pub token_file: Option<String>,
     /// The replies and messages that this bot should react to.
+    #[serde(default)]
     pub responses: Vec<Response>,
+    /// How to choose a response, if the triggers of multiple responses are found in a message.
+    #[serde(default)]
+    pub match_policy: MatchPolicy,
+    /// How messages and triggers are normalized before they are compared,
+    /// if a response does not configure it itself.
+    #[serde(default)]
+    pub normalize: Vec<Step>,
+    /// The probability (`0.0` to `1.0`) with which a found response is replied,
+    /// if a response does not configure it itself. Default: 1.0
+    #[serde(default = "default_chance")]
+    pub chance: f64,
+    /// The minimum number of messages that have to be sent in a channel, before this bot
+    /// replies in it again. Default: 0
+    #[serde(default)]
+    pub min_messages_between_replies: u32,
+    /// The timezone in which the schedules of the responses are evaluated, e.g. `Europe/Berlin`.
+    /// Default: The local timezone of the server
+    #[schemars(with = "Option<String>")]
+    pub timezone: Option<Tz>,
+    /// If this bot should also search edited messages for triggers and edit its replies to them.
+    /// Default: false
+    #[serde(default)]
+    pub reply_to_edits: bool,
+    /// If this bot should delete its replies, when the message they reply to is deleted.
+    /// Default: false
+    #[serde(default)]
+    pub delete_orphaned_replies: bool,
+    /// The number of messages that can be sent in a channel during a sequence of replies,
+    /// before the rest of the sequence is cancelled. The first message is always sent. Default: 3
+    #[serde(default = "default_cancel_sequences_after")]
+    pub cancel_sequences_after: u32,
+    /// If set, this bot shows the typing indicator and waits a bit before every reply, as if a
+    /// human would type it.
+    pub human_delay: Option<HumanDelay>,
+    /// If the replies of this bot can be deleted by reacting with 🗑️ to them. Only the user who
+    /// triggered the reply and moderators can delete it. Default: false
+    #[serde(default)]
+    pub delete_reaction: bool,
+    /// Messages that this bot posts on its own at scheduled times
+    #[serde(default)]
+    pub scheduled: Vec<ScheduledPost>,
+    /// The seconds after which the embeds with converted euros are deleted again.
+    pub embed_delete_after: Option<f64>,
+    /// The value of one unit of a foreign currency in euros, by its ISO 4217 code (USD, CHF,
+    /// GBP). Amounts in these currencies are converted to euros first.
+    #[serde(default = "default_exchange_rates")]
+    pub exchange_rates: BTreeMap<String, f64>,
+    /// If amounts are converted to the currency of a year (D-Mark, Reichsmark, Rentenmark) and to
+    /// today's purchasing power, when the message mentions the year. Default: false
+    #[serde(default)]
+    pub historical: bool,
+    /// The maximum number of amounts in a message which are converted. Default: 3
+    #[serde(default = "default_max_conversions")]
+    pub max_conversions: usize,
+    /// The messages with which amounts of money are answered
+    #[serde(default)]
+    pub conversion_messages: ConversionMessages,
+    /// Rules that convert other units into something absurd, e.g. kilometers to football fields
+    #[serde(default)]
+    pub converters: Vec<Converter>,
+    /// The quotes, which can be found with `/zitat` and are posted as "Zitat des Tages"
+    #[serde(default)]
+    pub quotes: QuoteConfig,
+    /// The compiled triggers of all responses. It is built when the config is loaded.
+    #[serde(skip)]
+    pub matcher: Arc<Matcher<Response>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 /// Structures the data used by the Autkommentator bot
 pub struct Autokommentator {
     /// The token that is used to log into discord
-    pub token: Option<String>,
+    #[schemars(with = "Option<String>")]
+    pub token: Option<Secret>,
+    /// A file that contains the token, e.g. a docker secret.
+    /// Only used, if `token` is not set.
+    pub token_file: Option<String>,
     /// The replies and messages that this bot should react to.
+    #[serde(default)]
     pub responses: Vec<Response>,
+    /// How to choose a response, if the triggers of multiple responses are found in a message.
+    #[serde(default)]
+    pub match_policy: MatchPolicy,
+    /// How messages and triggers are normalized before they are compared,
+    /// if a response does not configure it itself.
+    #[serde(default)]
+    pub normalize: Vec<Step>,
+    /// The probability (`0.0` to `1.0`) with which a found response is replied,
+    /// if a response does not configure it itself. Default: 1.0
+    #[serde(default = "default_chance")]
+    pub chance: f64,
+    /// The minimum number of messages that have to be sent in a channel, before this bot
+    /// replies in it again. Default: 0
+    #[serde(default)]
+    pub min_messages_between_replies: u32,
+    /// The timezone in which the schedules of the responses are evaluated, e.g. `Europe/Berlin`.
+    /// Default: The local timezone of the server
+    #[schemars(with = "Option<String>")]
+    pub timezone: Option<Tz>,
+    /// If this bot should also search edited messages for triggers and edit its replies to them.
+    /// Default: false
+    #[serde(default)]
+    pub reply_to_edits: bool,
+    /// If this bot should delete its replies, when the message they reply to is deleted.
+    /// Default: false
+    #[serde(default)]
+    pub delete_orphaned_replies: bool,
+    /// The number of messages that can be sent in a channel during a sequence of replies,
+    /// before the rest of the sequence is cancelled. The first message is always sent. Default: 3
+    #[serde(default = "default_cancel_sequences_after")]
+    pub cancel_sequences_after: u32,
+    /// If set, this bot shows the typing indicator and waits a bit before every reply, as if a
+    /// human would type it.
+    pub human_delay: Option<HumanDelay>,
+    /// If the replies of this bot can be deleted by reacting with 🗑️ to them. Only the user who
+    /// triggered the reply and moderators can delete it. Default: false
+    #[serde(default)]
+    pub delete_reaction: bool,
+    /// Messages that this bot posts on its own at scheduled times
+    #[serde(default)]
+    pub scheduled: Vec<ScheduledPost>,
+    /// The guilds whose messages this bot learns from, to generate replies
+    #[serde(default)]
+    pub markov: MarkovConfig,
+    /// The compiled triggers of all responses. It is built when the config is loaded.
+    #[serde(skip)]
+    pub matcher: Arc<Matcher<Response>>,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+/// The Markov chain of the Autokommentator, from which replies can be generated
+pub struct MarkovConfig {
+    /// The IDs of the guilds in whose messages the Autokommentator learns. Nothing is learned in
+    /// other guilds. Default: None
+    #[serde(default)]
+    pub guilds: Vec<u64>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 /// Structures the data on how to react to messages
 pub struct Response {
     /// A list of strings that trigger a reaction in a message
+    #[schemars(with = "Vec<String>")]
     pub trigger: value::Array,
-    /// A list of strings that are replied to the message:
+    /// A list of replies to the message: Either strings or sequences of messages.
     /// If there are multiple elements in this list, one is selected randomly.
+    #[schemars(with = "Vec<Reply>")]
     pub response_pool: value::Array,
+    /// Responses with a higher priority are preferred by [MatchPolicy::HighestPriority] and
+    /// [MatchPolicy::AllCombined]/[MatchPolicy::AllSeparate]. Default: 0
+    #[serde(default)]
+    pub priority: i32,
+    /// How messages and triggers are normalized before they are compared.
+    /// If it is not set, the setting of the bot is used.
+    pub normalize: Option<Vec<Step>>,
+    /// Enables fuzzy matching: The maximum number of typos (edits) per word of a trigger.
+    /// Words with less than 4 characters always have to match exactly.
+    pub fuzzy: Option<usize>,
+    /// The probability (`0.0` to `1.0`) with which this response is replied, if it was found.
+    /// If it is not set, the setting of the bot is used.
+    pub chance: Option<f64>,
+    /// When this response is active. If it is not set, the response is always active.
+    pub active: Option<Schedule>,
+    /// The seconds after which the reply is deleted again.
+    /// If multiple responses are combined into one reply, the shortest time is used.
+    pub delete_after: Option<f64>,
+    /// The config file in which this response was defined.
+    #[serde(skip)]
+    pub origin: String,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(untagged)]
+/// An entry of the response pool of a [Response]
+pub enum Reply {
+    /// A single message
+    Message(String),
+    /// Multiple messages, which are sent one after another, e.g. a setup and a punchline
+    Sequence(Vec<SequenceStep>),
+    /// A message that is generated when the reply is sent, e.g.
+    /// `{ generator = "markov", max_words = 20 }`
+    Generated {
+        /// What generates the message
+        generator: Generator,
+        /// The maximum number of words of the message. Default: 20
+        #[serde(default = "default_max_words")]
+        max_words: usize,
+    },
+}
+
+#[derive(Deserialize, JsonSchema, Copy, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+/// What generates the message of a [Reply::Generated]
+pub enum Generator {
+    /// A Markov chain, which is learned from the messages of the guild (see [MarkovConfig])
+    Markov,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(untagged)]
+/// A single message of a [Reply::Sequence]
+pub enum SequenceStep {
+    /// A message that is sent right after the previous one
+    Message(String),
+    /// A message that is sent after a delay
+    Delayed {
+        /// The content of the message
+        message: String,
+        /// The seconds to wait before the message is sent. The typing indicator is shown in the
+        /// meantime. Default: 0
+        #[serde(default)]
+        delay: f64,
+    },
+}
+
+#[derive(Deserialize, JsonSchema, Copy, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+/// How long a bot waits before it replies, depending on the length of the reply
+pub struct HumanDelay {
+    /// The seconds it takes to type one character. Default: 0.05
+    #[serde(default = "default_seconds_per_character")]
+    pub seconds_per_character: f64,
+    /// The delay is randomly changed by up to this fraction of it, e.g. `0.25` for ±25%.
+    /// Default: 0.25
+    #[serde(default = "default_jitter")]
+    pub jitter: f64,
+    /// The maximum number of seconds to wait. Default: 5
+    #[serde(default = "default_max_delay")]
+    pub max: f64,
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+/// The messages with which the Känguru Knecht answers amounts of money
+pub struct ConversionMessages {
+    /// The answer to an amount of euros. `{amount}`, `{mark}`, `{ostmark}` and
+    /// `{schwarzmarkt}` are replaced by the amount and its conversions.
+    #[serde(default = "default_conversion_message")]
+    pub conversion: String,
+    /// The answer to an amount of more than 100,000 euros
+    #[serde(default = "default_too_big_message")]
+    pub too_big: String,
+    /// The answer to a message which mentions a currency, but no amount. Default: No answer
+    pub not_found: Option<String>,
+}
+
+impl Default for ConversionMessages {
+    fn default() -> Self {
+        ConversionMessages {
+            conversion: default_conversion_message(),
+            too_big: default_too_big_message(),
+            not_found: None,
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+/// The quotes of the Känguru Knecht
+pub struct QuoteConfig {
+    /// A file with quotes in TOML, YAML or JSON, like `assets/quotes.toml`.
+    /// Default: The quotes bundled with the program
+    pub file: Option<String>,
+    /// The ID of the channel in which the "Zitat des Tages" is posted. Default: None
+    pub daily_channel: Option<u64>,
+    /// The hour (`0` to `23`) at which the "Zitat des Tages" is posted, in the timezone of the
+    /// bot. Default: 9
+    #[serde(default = "default_daily_hour")]
+    pub daily_hour: u32,
+    /// The quotes from `file` or the bundled quotes. They are loaded when the config is loaded.
+    #[serde(skip)]
+    pub quotes: Vec<Quote>,
+}
+
+impl Default for QuoteConfig {
+    fn default() -> Self {
+        QuoteConfig {
+            file: None,
+            daily_channel: None,
+            daily_hour: default_daily_hour(),
+            quotes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Copy, Clone, Default, Eq, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+/// How a bot chooses a response, if the triggers of multiple responses are found in a message
+pub enum MatchPolicy {
+    /// Use the response that comes first in the config file
+    #[default]
+    FirstMatch,
+    /// Use the response with the highest priority.
+    /// If multiple responses have the same priority, the first one is used.
+    HighestPriority,
+    /// Use the response with the longest trigger that was found.
+    /// If multiple triggers have the same length, the first one is used.
+    LongestMatch,
+    /// Reply with all responses, ordered by their priority, combined into one message
+    AllCombined,
+    /// Reply with all responses, ordered by their priority, each in its own message
+    AllSeparate,
+}
+
+impl MatchPolicy {
+    /// Selects the responses to reply with from all responses found in a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `matches`: All responses found in a message and their trigger, in config file order.
+    ///   See [Matcher::find_all].
+    ///
+    /// returns: Vec<Match<Response>>
+    pub fn select<'a>(&self, mut matches: Vec<Match<'a, Response>>) -> Vec<Match<'a, Response>> {
+        match self {
+            MatchPolicy::FirstMatch => matches.truncate(1),
+            MatchPolicy::HighestPriority => {
+                // Reverse, so that max_by_key returns the first of equal elements
+                matches = matches
+                    .into_iter()
+                    .rev()
+                    .max_by_key(|m| m.item.priority)
+                    .into_iter()
+                    .collect();
+            }
+            MatchPolicy::LongestMatch => {
+                matches = matches
+                    .into_iter()
+                    .rev()
+                    .max_by_key(|m| m.trigger.chars().count())
+                    .into_iter()
+                    .collect();
+            }
+            MatchPolicy::AllCombined | MatchPolicy::AllSeparate => {
+                // The sort is stable, so equal priorities keep their config file order
+                matches.sort_by_key(|m| Reverse(m.item.priority));
+            }
+        }
+        matches
+    }
+}
+
+impl Reply {
+    /// Gets the messages of this reply in the order they are sent.
+    ///
+    /// A [Reply::Generated] has no messages, until it is generated by [crate::markov::resolve].
+    pub fn steps(&self) -> Vec<SequenceStep> {
+        match self {
+            Reply::Message(message) => vec![SequenceStep::Message(message.clone())],
+            Reply::Sequence(steps) => steps.clone(),
+            Reply::Generated { .. } => Vec::new(),
+        }
+    }
+}
+
+impl SequenceStep {
+    /// The content of this message
+    pub fn content(&self) -> &str {
+        match self {
+            SequenceStep::Message(message) => message,
+            SequenceStep::Delayed { message, .. } => message,
+        }
+    }
+
+    /// How long to wait before this message is sent
+    pub fn delay(&self) -> Duration {
+        match self {
+            SequenceStep::Message(_) => Duration::ZERO,
+            SequenceStep::Delayed { delay, .. } => Duration::from_secs_f64(*delay),
+        }
+    }
+}
+
+impl HumanDelay {
+    /// Calculates how long it would take a human to type a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `content`: The message to type
+    ///
+    /// returns: Duration
+    pub fn delay(&self, content: &str) -> Duration {
+        let seconds = content.chars().count() as f64 * self.seconds_per_character;
+        let jitter = rand::thread_rng().gen_range(-self.jitter..=self.jitter);
+        Duration::from_secs_f64((seconds * (1.0 + jitter)).clamp(0.0, self.max))
+    }
 }
 
 impl Clone for Response {
@@ -80,10 +487,48 @@ impl Clone for Response {
         Response {
             trigger: self.trigger.to_vec(),
             response_pool: self.response_pool.to_vec(),
+            priority: self.priority,
+            normalize: self.normalize.clone(),
+            fuzzy: self.fuzzy,
+            chance: self.chance,
+            active: self.active.clone(),
+            delete_after: self.delete_after,
+            origin: self.origin.clone(),
         }
     }
 }
 
+impl Searchable for Response {
+    fn triggers(&self) -> Vec<String> {
+        self.trigger
+            .iter()
+            .filter_map(|trigger| trigger.as_str().map(String::from))
+            .collect()
+    }
+
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::new(self.normalize.as_deref().unwrap_or_default())
+    }
+
+    fn max_distance(&self) -> Option<usize> {
+        self.fuzzy.filter(|distance| *distance > 0)
+    }
+}
+
+impl Kaenguru {
+    /// Gets the token of this bot from either `token` or `token_file`.
+    pub fn token(&self) -> Result<Option<Secret>, String> {
+        secrets::read_token(&self.token, &self.token_file)
+    }
+}
+
+impl Autokommentator {
+    /// Gets the token of this bot from either `token` or `token_file`.
+    pub fn token(&self) -> Result<Option<Secret>, String> {
+        secrets::read_token(&self.token, &self.token_file)
+    }
+}
+
 impl Config {
     /// Creates a configuration object from a config file.
     ///
@@ -93,22 +538,14 @@ impl Config {
     /// **This function should be only called once when the program is started, as
     /// always reading the file in again takes a lot of time.**
     pub fn new() -> Self {
-        // Read in config file location
-        let config_file = match env::var("CONFIG_FILE") {
-            Ok(o) => {
-                warn!("Config file location has been overridden to: \"{}\"", o);
-                o
-            }
-            Err(_) => {
-                debug!("Config file location has not changed. Using default \"config.toml\"...");
-                "config.toml".to_string()
-            }
-        };
+        let config_file = config_file_location();
+        let format = Format::from_path(&config_file);
+        debug!("Config file format is {:?}", format);
 
         if !Path::new(&config_file).exists() {
             warn!("Could not locate Configuration file! Using defaults!");
 
-            make_default_config(&config_file);
+            make_default_config(&config_file, format);
         }
 
         let config_content = match fs::read_to_string(&config_file) {
@@ -116,12 +553,43 @@ impl Config {
             Ok(s) => s,
         };
         debug!("Configuration has been loaded!");
-        trace!("Contents:\n{}", config_content);
+        trace!("Contents:\n{}", secrets::redact(&config_content));
+
+        info!("Parsing configuration...");
+        let mut value: toml::Value = match format.parse(&config_content) {
+            Err(why) => invalid_config(&config_file, format, why),
+            Ok(value) => value,
+        };
 
         info!("Checking version of the configuration...");
-        {
-            let (compatible, version) = check_version(&config_content);
-            if !compatible {
+        let version = read_version(&value);
+        if !check_version(&version) {
+            if format != Format::Toml {
+                error!(
+                    "Config files can only be migrated automatically, if they are written in TOML."
+                );
+            } else {
+                warn!(
+                    "The config file version ({}) is outdated. Trying to migrate it...",
+                    version
+                );
+                match migrate::migrate(&config_content, &version) {
+                    Ok(migrated) => {
+                        // The migrated config can be used, even if it can not be saved
+                        if let Err(why) = migrate::write(&config_file, &version, &migrated) {
+                            warn!("Could not save the migrated config file: {}", why);
+                            warn!("The config file will be migrated again on the next start.");
+                        }
+                        value = match format.parse(&migrated) {
+                            Err(why) => invalid_config(&config_file, format, why),
+                            Ok(value) => value,
+                        }
+                    }
+                    Err(why) => error!("Could not migrate the config file: {}", why),
+                }
+            }
+            let version = read_version(&value);
+            if !check_version(&version) {
                 error!("The config file version ({}) is not compatible with your program version ({}.{})!\nPlease inspect the Changelog (https://github.com/MaFeLP/discord_bots/releases) and see how to change the config file accordingly!",
                           version,
                           env!("CARGO_PKG_VERSION_MAJOR"),
@@ -132,27 +600,220 @@ impl Config {
         }
         info!("Configuration version compatible!");
 
-        info!("Parsing configuration...");
-        let out: Config = match toml::from_str(&config_content) {
-            Err(_) => {
-                let example_config_file = format!("{}.example", config_file);
-                make_default_config(&example_config_file);
-                error!("Configuration file invalid!");
-                error!("An example can be found here: {}", example_config_file);
+        let origins = match include::merge_includes(&config_file, &mut value) {
+            Ok(origins) => origins,
+            Err(why) => {
+                error!("{}", why);
                 exit(1);
             }
+        };
+        if let Err(why) = secrets::interpolate(&mut value) {
+            error!("{}", why);
+            exit(1);
+        }
+        let mut out: Config = match value.try_into() {
+            Err(why) => match include::find_invalid(&origins) {
+                Some((file, why)) => {
+                    error!("Configuration file invalid!");
+                    error!("{}: {}", file, why);
+                    exit(1);
+                }
+                None => invalid_config(&config_file, format, why),
+            },
             Ok(config) => config,
         };
+        for (response, origin) in out
+            .autokommentator
+            .responses
+            .iter_mut()
+            .zip(origins.autokommentator)
+        {
+            response.origin = origin;
+        }
+        for (response, origin) in out.kaenguru.responses.iter_mut().zip(origins.kaenguru) {
+            response.origin = origin;
+        }
+
+        info!("Validating configuration...");
+        let errors = validate::validate(&out);
+        if !errors.is_empty() {
+            for why in errors {
+                error!("{}", why);
+            }
+            error!("Configuration file invalid!");
+            exit(1);
+        }
+
+        debug!("Loading quotes...");
+        match quotes::load(&out.kaenguru.quotes.file) {
+            Ok(quotes) => out.kaenguru.quotes.quotes = quotes,
+            Err(why) => {
+                error!("{}", why);
+                error!("Configuration file invalid!");
+                exit(1);
+            }
+        }
+
+        debug!("Building trigger index...");
+        // Responses without their own normalization or chance use the one of their bot
+        for response in out.autokommentator.responses.iter_mut() {
+            response
+                .normalize
+                .get_or_insert_with(|| out.autokommentator.normalize.clone());
+            response.chance.get_or_insert(out.autokommentator.chance);
+        }
+        for response in out.kaenguru.responses.iter_mut() {
+            response
+                .normalize
+                .get_or_insert_with(|| out.kaenguru.normalize.clone());
+            response.chance.get_or_insert(out.kaenguru.chance);
+        }
+        out.autokommentator.matcher =
+            Arc::new(Matcher::new(out.autokommentator.responses.to_vec()));
+        out.kaenguru.matcher = Arc::new(Matcher::new(out.kaenguru.responses.to_vec()));
+        debug!("Using configuration version {}", out.version);
         info!("Configuration now usable!");
         out
     }
 }
 
+/// Bots reply to every message with a trigger, if no chance is configured
+fn default_chance() -> f64 {
+    1.0
+}
+
+/// Sequences are cancelled, if the channel gets busy
+fn default_cancel_sequences_after() -> u32 {
+    3
+}
+
+/// Humans need about 50ms to type a character
+fn default_seconds_per_character() -> f64 {
+    0.05
+}
+
+/// The delay differs by up to 25% between replies
+fn default_jitter() -> f64 {
+    0.25
+}
+
+/// Long replies are not delayed for more than 5 seconds
+fn default_max_delay() -> f64 {
+    5.0
+}
+
+/// The Zitat des Tages is posted in the morning
+fn default_daily_hour() -> u32 {
+    9
+}
+
+/// The classic joke of the Känguru
+fn default_conversion_message() -> String {
+    "{amount} Das, das sind ja {mark} Mark! {ostmark} Ostmark! {schwarzmarkt} Ostmark aufm Schwarzmarkt!".to_string()
+}
+
+/// The DDR did not have that much debt
+fn default_too_big_message() -> String {
+    "Huiuiui! So viele Schulden kann die DDR doch nicht haben!".to_string()
+}
+
+/// Messages with more amounts are rare, and the embed would get long
+fn default_max_conversions() -> usize {
+    3
+}
+
+/// Roughly the exchange rates of 2024. They are only used for jokes, so they do not need to be
+/// up to date.
+fn default_exchange_rates() -> BTreeMap<String, f64> {
+    BTreeMap::from([
+        ("USD".to_string(), 0.92),
+        ("CHF".to_string(), 1.04),
+        ("GBP".to_string(), 1.17),
+    ])
+}
+
+/// Generated replies should be about as long as a comment
+fn default_max_words() -> usize {
+    20
+}
+
+/// Generates a JSON Schema of the configuration file.
+///
+/// The schema is derived from the [Config] struct and its fields, so it is always in sync with
+/// the configuration this program understands. Editors with a TOML language server like
+/// [taplo](https://taplo.tamasfe.dev/) can use it to validate and autocomplete the config file.
+///
+/// # Arguments
+///
+/// * `fragment`: If the schema is for the files that are included into the main config file
+///   (see [include]). In these files, no section is required.
+///
+/// returns: String
+pub fn json_schema(fragment: bool) -> String {
+    let mut schema = schemars::schema_for!(Config);
+    let root = schema.schema.object();
+
+    // The include list is consumed before deserializing, so it is not a field of [Config]
+    let mut include = SchemaGenerator::default()
+        .subschema_for::<Vec<String>>()
+        .into_object();
+    include.metadata().description = Some(
+        "Glob patterns of other config files, which are merged into this one in lexical order."
+            .to_string(),
+    );
+    root.properties
+        .insert("include".to_string(), Schema::Object(include));
+
+    if fragment {
+        root.required.clear();
+    }
+    serde_json::to_string_pretty(&schema).unwrap()
+}
+
+/// Gets the location of the configuration file.
+///
+/// The location is read from the environment `CONFIG_FILE`,
+/// or if it was not set, defaults to `config.toml`.
+///
+/// returns: String
+pub fn config_file_location() -> String {
+    match env::var("CONFIG_FILE") {
+        Ok(o) => {
+            warn!("Config file location has been overridden to: \"{}\"", o);
+            o
+        }
+        Err(_) => {
+            debug!("Config file location has not changed. Using default \"config.toml\"...");
+            "config.toml".to_string()
+        }
+    }
+}
+
+/// Tells the user that the configuration file is invalid, creates an example
+/// configuration next to it and exits the program.
+///
+/// # Arguments
+///
+/// * `config_file`: The location of the invalid config file.
+/// * `format`: The format of the config file.
+/// * `why`: The reason why the config file is invalid.
+///
+/// returns: !
+fn invalid_config(config_file: &str, format: Format, why: impl fmt::Display) -> ! {
+    let example_config_file = format!("{}.example", config_file);
+    make_default_config(&example_config_file, format);
+    error!("Configuration file invalid!");
+    error!("{}: {}", config_file, why);
+    error!("An example can be found here: {}", example_config_file);
+    exit(1);
+}
+
 /// A function to make a configuration file.
 ///
 /// # Arguments
 ///
 /// * `config_file`: The location where the new config file should be placed.
+/// * `format`: The format in which the config file should be written.
 ///
 /// returns: ()
 ///
@@ -160,10 +821,9 @@ impl Config {
 ///
 /// ```
 /// let config_file: String = String::from("config.toml");
-/// let future = make_default_config(&config_file);
-/// block_on(future);
+/// make_default_config(&config_file, Format::Toml);
 /// ```
-fn make_default_config(config_file: &String) {
+fn make_default_config(config_file: &str, format: Format) {
     debug!("Creating default configuration...");
     trace!("Creating configuration file...");
     // Try to create the file
@@ -177,11 +837,16 @@ fn make_default_config(config_file: &String) {
 
     trace!("Writing defaults to file...");
     // Write the config file
-    match writeln!(
-        &mut file,
-        "# Config created automatically\n{}",
-        include_str!("../config.toml.example")
-    ) {
+    let result = match format {
+        // JSON does not support comments
+        Format::Json => writeln!(&mut file, "{}", format.example()),
+        _ => writeln!(
+            &mut file,
+            "# Config created automatically\n{}",
+            format.example()
+        ),
+    };
+    match result {
         Ok(_) => println!("Written default configuration to {}", config_file),
         Err(why) => panic!(
             "Could not write default configuration file to {}: {:?}\n{}\n{}",
@@ -191,38 +856,56 @@ fn make_default_config(config_file: &String) {
     debug!("Default configuration has been created!");
 }
 
-/// Checks if the inputted config is compatible with the program
+/// Reads the version of a parsed config file. Environment variables in it are replaced, like in
+/// all other strings of the config (see [secrets::interpolate]).
+///
+/// The program exits, if the config file does not contain a valid version.
+///
+/// # Arguments
+///
+/// * `value`: The parsed config file
+///
+/// returns: String
+fn read_version(value: &toml::Value) -> String {
+    match value.get("version").map(toml::Value::as_str) {
+        Some(Some(version)) => match secrets::interpolate_str(version) {
+            Ok(version) => {
+                debug!("Found version in config file: {}", version);
+                version
+            }
+            Err(why) => {
+                error!("{}", why);
+                exit(1);
+            }
+        },
+        Some(None) => {
+            error!("The version in your config file has to be a string, e.g. \"0.3\"!");
+            exit(1);
+        }
+        None => {
+            error!("Could not find a version in your config file!");
+            error!("{}", PANIC_RESPONSE);
+            exit(1);
+        }
+    }
+}
+
+/// Checks if the version of a config file is compatible with the program
 ///
 /// # Arguments
 ///
-/// * `config_content`: The content of the config file to check for compatibility
+/// * `version`: The version of the config file, as read by [read_version]
 ///
-/// returns: (bool, String)
+/// returns: bool
 ///
 /// # Examples
 ///
 /// ```
-/// if ! check_version("config.toml")[0] {
+/// if !check_version(&read_version(&value)) {
 ///     panic!("Config version incompatible!");
 /// }
 /// ```
-fn check_version(config_content: &str) -> (bool, &str) {
-    // Get the version, by searching for the config line and getting the version part of it.
-    let version = {
-        let captures = match regex!("(?m)^version *= *\"(?P<version>\\d*\\.\\d*)\" *(|#.*)$")
-            .captures(config_content)
-        {
-            Some(c) => c,
-            None => {
-                error!("Could not find a version in your config file!");
-                error!("{}", PANIC_RESPONSE);
-                exit(1);
-            }
-        };
-        let out = captures.name("version").unwrap().as_str();
-        debug!("Found version in config file: {}", out);
-        out
-    };
+fn check_version(version: &str) -> bool {
     // Config file is always compatible with its associated program version
     if format!(
         "{}.{}",
@@ -235,7 +918,7 @@ fn check_version(config_content: &str) -> (bool, &str) {
             "Config file version ({}) is the same as program version ({}). Result: Compatible",
             version, version
         );
-        return (true, version);
+        return true;
     }
     // If config file version is not the program version, check if it is still compatible:
     for (major, minor) in COMPATIBLE_VERSIONS {
@@ -245,16 +928,17 @@ fn check_version(config_content: &str) -> (bool, &str) {
                 major,
                 minor
             );
-            return (true, version);
+            return true;
         }
     }
-    return (false, version);
+    false
 }
 
 ///
 /// An enum that represents all bots in this project. This enables non bot-specific functions
 /// (For example [crate::replies::reply_to]) to behave differently based on which bot is used.
 ///
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Bots {
     /// Represents the Autokommentator bot found in [xd.rs](crate::xd)
     Autokommentator,
@@ -0,0 +1,48 @@
+//!
+//! This module contains the configuration of the unit converters of the Känguru Knecht.
+//!
+//! A [Converter] finds amounts of a unit in a message with a regular expression, multiplies them
+//! with a factor and answers with a template, e.g.
+//! `{ pattern = '(?P<amount>\d+(?:,\d+)?) ?km\b', factor = 9.5, template = "{amount} km? Das sind ja {result} Fußballfelder!" }`.
+//!
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+/// A rule that converts amounts of a unit into something absurd, e.g. kilometers to football
+/// fields
+pub struct Converter {
+    /// The regular expression that finds the amounts in a message. The number has to be in a
+    /// group named `amount`, e.g. `(?P<amount>\d+(?:,\d+)?) ?km\b`
+    #[schemars(with = "String")]
+    pub pattern: Pattern,
+    /// The amount is multiplied with this factor
+    pub factor: f64,
+    /// The answer. `{amount}` is replaced by the amount as it was written, and `{result}` by
+    /// the converted amount.
+    pub template: String,
+}
+
+/// A regular expression with a group named `amount`
+#[derive(Deserialize, Clone, Debug)]
+#[serde(try_from = "String")]
+pub struct Pattern(pub Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let regex = Regex::new(&value)
+            .map_err(|why| format!("\"{}\" is not a valid regular expression: {}", value, why))?;
+        if !regex.capture_names().any(|name| name == Some("amount")) {
+            return Err(format!(
+                "The regular expression \"{}\" has no group named amount",
+                value
+            ));
+        }
+        Ok(Pattern(regex))
+    }
+}
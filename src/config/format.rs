@@ -0,0 +1,119 @@
+//!
+//! This module handles the different file formats a config file can be written in.
+//!
+//! The format is detected by the extension of the file:
+//!
+//! * `.yaml` and `.yml`: [YAML](https://yaml.org/)
+//! * `.json`: [JSON](https://www.json.org/)
+//! * Everything else: [TOML](https://toml.io/)
+//!
+//! All formats are parsed into the same [toml::Value], so they have identical semantics.
+//! TOML has no null, so keys whose value is null in YAML (`key: ~` or `key:`) or JSON
+//! (`"key": null`) are treated as if they were not set.
+//!
+
+use std::path::Path;
+use toml::Value;
+
+/// The example configuration, from which the examples in all other formats are created
+const EXAMPLE: &str = include_str!("../../config.toml.example");
+
+/// The file formats a config file can be written in
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Format {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    /// Detects the format of a config file by its extension. Defaults to [Format::Toml].
+    ///
+    /// # Arguments
+    ///
+    /// * `file`: The location of the config file
+    ///
+    /// returns: Format
+    pub fn from_path(file: &str) -> Format {
+        match Path::new(file)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("yaml") | Some("yml") => Format::Yaml,
+            Some("json") => Format::Json,
+            _ => Format::Toml,
+        }
+    }
+
+    /// The file extensions that belong to this format
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Format::Toml => &["toml"],
+            Format::Yaml => &["yaml", "yml"],
+            Format::Json => &["json"],
+        }
+    }
+
+    /// Parses the contents of a config file in this format.
+    ///
+    /// # Arguments
+    ///
+    /// * `content`: The contents of the config file
+    ///
+    /// returns: Result<Value, String>
+    pub fn parse(&self, content: &str) -> Result<Value, String> {
+        match self {
+            Format::Toml => toml::from_str(content).map_err(|why| why.to_string()),
+            Format::Yaml => serde_yaml::from_str(content)
+                .map_err(|why| why.to_string())
+                .and_then(without_nulls),
+            Format::Json => serde_json::from_str(content)
+                .map_err(|why| why.to_string())
+                .and_then(without_nulls),
+        }
+    }
+
+    /// Gets the example configuration in this format.
+    ///
+    /// Only the TOML example contains comments that explain all options.
+    ///
+    /// returns: String
+    pub fn example(&self) -> String {
+        let value: Value = toml::from_str(EXAMPLE).unwrap();
+        match self {
+            Format::Toml => EXAMPLE.to_string(),
+            Format::Yaml => format!(
+                "# See config.toml.example for an explanation of all options\n{}",
+                serde_yaml::to_string(&value).unwrap()
+            ),
+            Format::Json => serde_json::to_string_pretty(&value).unwrap(),
+        }
+    }
+}
+
+/// Converts a parsed YAML or JSON value into a [toml::Value] and removes all keys whose value is
+/// null, as TOML can not express them.
+///
+/// # Arguments
+///
+/// * `value`: The parsed YAML or JSON value
+///
+/// returns: Result<Value, String>: An error, if a list contains null.
+fn without_nulls(mut value: serde_json::Value) -> Result<Value, String> {
+    remove_nulls(&mut value);
+    Value::try_from(value).map_err(|why| why.to_string())
+}
+
+/// Removes all keys whose value is null from the objects in a value. See [without_nulls].
+fn remove_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            object.retain(|_, value| !value.is_null());
+            object.values_mut().for_each(remove_nulls);
+        }
+        serde_json::Value::Array(array) => array.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
@@ -0,0 +1,312 @@
+//!
+//! This module splits the configuration across multiple files.
+//!
+//! Besides the main config file, the following files are read and merged into it:
+//!
+//! 1. All files matched by the glob patterns in the top level `include` list,
+//!    e.g. `include = ["responses/*.toml"]`.
+//! 2. All `*.toml`, `*.yaml`, `*.yml` and `*.json` files in the `conf.d` directory next to the
+//!    main config file.
+//!
+//! The format of every file is detected by its extension, see [Format].
+//!
+//! Both lists are read in lexical order. A file that is matched multiple times (or is the main
+//! config file itself) is only read once. When merging, tables are merged recursively and lists
+//! (like `responses`) are appended to each other. Other values of a later file override the
+//! values of an earlier file.
+//!
+
+use crate::config::{format::Format, secrets, Config};
+use glob::glob;
+use log::{debug, info, warn};
+use std::{collections::HashSet, fs, path::Path};
+use toml::{value::Table, Value};
+
+/// The name of the directory next to the config file, from which all files are included.
+const CONF_D: &str = "conf.d";
+
+/// The names of the bot sections in the config file
+const BOTS: [&str; 2] = ["autokommentator", "kaenguru"];
+
+/// Tracks from which file every response of a bot originates.
+///
+/// The index of an entry is the same as the index of the response in the merged config.
+#[derive(Default)]
+pub struct Origins {
+    /// The files of the responses of the Autokommentator
+    pub autokommentator: Vec<String>,
+    /// The files of the responses of the Känguru Knecht
+    pub kaenguru: Vec<String>,
+    /// Every included file and its contents, before it was merged
+    pub fragments: Vec<(String, Table)>,
+}
+
+impl Origins {
+    /// Records the responses of all bots of a newly read file.
+    fn record(&mut self, file: &str, value: &Table) {
+        for bot in BOTS {
+            let count = value
+                .get(bot)
+                .and_then(|bot| bot.get("responses"))
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            let origins = match bot {
+                "autokommentator" => &mut self.autokommentator,
+                _ => &mut self.kaenguru,
+            };
+            origins.extend(std::iter::repeat_n(file.to_string(), count));
+        }
+    }
+}
+
+/// Reads all included files and the `conf.d` directory and merges them into the main config.
+///
+/// # Arguments
+///
+/// * `config_file`: The location of the main config file. Includes are relative to its directory.
+/// * `config`: The already parsed contents of the main config file.
+///
+/// returns: Result<Origins, String>: The files from which all responses originate.
+///
+/// # Examples
+///
+/// ```
+/// let mut value = Format::Toml.parse(&config_content).unwrap();
+/// let origins = include::merge_includes("config.toml", &mut value)?;
+/// ```
+pub fn merge_includes(config_file: &str, config: &mut Value) -> Result<Origins, String> {
+    let config = match config {
+        Value::Table(table) => table,
+        _ => return Err(format!("{} does not contain a table", config_file)),
+    };
+    let mut origins = Origins::default();
+    origins.record(config_file, config);
+
+    let directory = Path::new(config_file)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let mut files: Vec<String> = Vec::new();
+
+    // Files from the include list
+    let patterns = match config.remove("include") {
+        Some(Value::Array(patterns)) => patterns.to_vec(),
+        Some(_) => return Err("include has to be a list of file patterns".to_string()),
+        None => Vec::new(),
+    };
+    for pattern in patterns {
+        let pattern = match pattern.as_str() {
+            Some(s) => s,
+            None => return Err(format!("{} is not a valid file pattern", pattern)),
+        };
+        files.extend(expand(&directory.join(pattern))?);
+    }
+
+    // Files from the conf.d directory
+    let conf_d = directory.join(CONF_D);
+    if conf_d.is_dir() {
+        debug!("Including all files from {}", conf_d.display());
+        let mut conf_d_files = Vec::new();
+        for format in [Format::Toml, Format::Yaml, Format::Json] {
+            for extension in format.extensions() {
+                conf_d_files.extend(expand(&conf_d.join(format!("*.{}", extension)))?);
+            }
+        }
+        conf_d_files.sort();
+        files.extend(conf_d_files);
+    }
+
+    // The main config file is already read
+    let mut read: HashSet<_> = fs::canonicalize(config_file).into_iter().collect();
+    for file in files {
+        if let Ok(path) = fs::canonicalize(&file) {
+            if !read.insert(path) {
+                debug!("Config file {} is already included", file);
+                continue;
+            }
+        }
+        info!("Including config file {}", file);
+        let content = fs::read_to_string(&file)
+            .map_err(|why| format!("Could not read included file {}: {}", file, why))?;
+        let table = match Format::from_path(&file).parse(&content) {
+            Ok(Value::Table(table)) => table,
+            Ok(_) => return Err(format!("Included file {} does not contain a table", file)),
+            Err(why) => return Err(format!("Included file {} is invalid: {}", file, why)),
+        };
+        if table.contains_key("include") {
+            warn!(
+                "{}: Included files can not include other files. Ignoring its include list.",
+                file
+            );
+        }
+        origins.record(&file, &table);
+        origins.fragments.push((file.clone(), table.clone()));
+        merge(config, table, &file);
+    }
+
+    Ok(origins)
+}
+
+/// Deserializes every included file on its own, to find the file that makes the merged config
+/// invalid. The sections that every config needs are added to the files, if they are missing.
+///
+/// # Arguments
+///
+/// * `origins`: The origins returned by [merge_includes]
+///
+/// returns: Option<(String, String)>: The first invalid file and the reason why it is invalid.
+pub fn find_invalid(origins: &Origins) -> Option<(String, String)> {
+    origins.fragments.iter().find_map(|(file, fragment)| {
+        let mut value = fragment.clone();
+        value.remove("include");
+        value
+            .entry("version")
+            .or_insert_with(|| Value::String(String::new()));
+        for bot in BOTS {
+            value
+                .entry(bot)
+                .or_insert_with(|| Value::Table(Table::new()));
+        }
+        let mut value = Value::Table(value);
+        secrets::interpolate(&mut value)
+            .and_then(|_| {
+                value
+                    .try_into::<Config>()
+                    .map(|_| ())
+                    .map_err(|why| why.to_string())
+            })
+            .err()
+            .map(|why| (file.clone(), why))
+    })
+}
+
+/// Gets all files matching a glob pattern in lexical order.
+fn expand(pattern: &Path) -> Result<Vec<String>, String> {
+    let pattern = pattern.to_string_lossy();
+    let paths =
+        glob(&pattern).map_err(|why| format!("Invalid include pattern {}: {}", pattern, why))?;
+    let mut files: Vec<String> = paths
+        .filter_map(Result::ok)
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+    if files.is_empty() {
+        warn!("The include pattern {} does not match any files", pattern);
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Merges `other` into `base`. See [the module documentation](self) for how values are merged.
+fn merge(base: &mut Table, other: Table, file: &str) {
+    for (key, value) in other {
+        if key == "include" {
+            continue;
+        }
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(other)) => merge(base, other, file),
+            (Some(Value::Array(base)), Value::Array(other)) => base.extend(other),
+            (Some(_), value) => {
+                warn!("{}: Overriding the value of \"{}\"", file, key);
+                base.insert(key, value);
+            }
+            (None, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a TOML table
+    fn table(content: &str) -> Table {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn values_are_merged() {
+        let cases = [
+            // Base, other file, merged
+            ("a = 1", "b = 2", "a = 1\nb = 2"),
+            ("a = 1", "a = 2", "a = 2"),
+            ("a = [1]", "a = [2, 3]", "a = [1, 2, 3]"),
+            ("a = [1]", "a = 2", "a = 2"),
+            ("[a]\nb = 1\nc = 1", "[a]\nc = 2\nd = 2", "[a]\nb = 1\nc = 2\nd = 2"),
+            (
+                "[[kaenguru.responses]]\ntrigger = [\"a\"]",
+                "[[kaenguru.responses]]\ntrigger = [\"b\"]",
+                "[[kaenguru.responses]]\ntrigger = [\"a\"]\n[[kaenguru.responses]]\ntrigger = [\"b\"]",
+            ),
+            // Included files can not include other files
+            ("include = [\"a\"]", "include = [\"b\"]", "include = [\"a\"]"),
+        ];
+        for (base, other, expected) in cases {
+            let mut merged = table(base);
+            merge(&mut merged, table(other), "other.toml");
+            assert_eq!(merged, table(expected), "{:?} + {:?}", base, other);
+        }
+    }
+
+    #[test]
+    fn included_files_are_merged_once() {
+        let directory = std::env::temp_dir().join(format!("xd_bot_include_{}", std::process::id()));
+        fs::create_dir_all(directory.join("responses")).unwrap();
+        fs::create_dir_all(directory.join(CONF_D)).unwrap();
+        let config_file = directory.join("config.toml");
+        let config = r#"
+            version = "0.3"
+            include = ["responses/*.toml", "responses/b.toml", "config.toml"]
+            [[autokommentator.responses]]
+            trigger = ["main"]
+            response_pool = ["main"]
+        "#;
+        fs::write(&config_file, config).unwrap();
+        fs::write(
+            directory.join("responses/b.toml"),
+            "[[autokommentator.responses]]\ntrigger = [\"b\"]\nresponse_pool = [\"b\"]",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("responses/a.toml"),
+            "[[kaenguru.responses]]\ntrigger = [\"a\"]\nresponse_pool = [\"a\"]",
+        )
+        .unwrap();
+        fs::write(
+            directory.join(CONF_D).join("10-c.json"),
+            r#"{"autokommentator": {"responses": [{"trigger": ["c"], "response_pool": ["c"]}]}}"#,
+        )
+        .unwrap();
+
+        let config_file = config_file.to_string_lossy().to_string();
+        let mut value = Format::Toml.parse(config).unwrap();
+        let origins = merge_includes(&config_file, &mut value).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(value.get("include").is_none());
+        let triggers = |bot: &str| -> Vec<String> {
+            value[bot]["responses"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|response| response["trigger"][0].as_str().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(triggers("autokommentator"), ["main", "b", "c"]);
+        assert_eq!(triggers("kaenguru"), ["a"]);
+
+        let file_name = |file: &String| {
+            Path::new(file)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        };
+        let autokommentator: Vec<String> = origins.autokommentator.iter().map(file_name).collect();
+        assert_eq!(autokommentator, ["config.toml", "b.toml", "10-c.json"]);
+        let kaenguru: Vec<String> = origins.kaenguru.iter().map(file_name).collect();
+        assert_eq!(kaenguru, ["a.toml"]);
+        assert_eq!(origins.fragments.len(), 3);
+    }
+}
@@ -0,0 +1,395 @@
+//!
+//! This module upgrades configuration files of older versions to the current version.
+//!
+//! Every [Migration] upgrades a config file by exactly one version. To upgrade a file that is
+//! multiple versions behind, all migrations are applied one after another. The file is edited
+//! with [toml_edit], so comments and formatting of the user are preserved wherever possible.
+//!
+//! A migration is only added, when the format of the config file changes in a way that older
+//! files can not be read anymore. All versions in [COMPATIBLE_VERSIONS] are read as they are.
+//!
+
+use crate::config::{check_version, format::Format, read_version, secrets, COMPATIBLE_VERSIONS};
+use log::{info, warn};
+use similar::TextDiff;
+use std::{fmt, fs, io};
+use toml_edit::{DocumentMut, Item};
+
+/// A single step that upgrades a config file from one version to the next.
+struct Migration {
+    /// The version this migration can be applied to
+    from: (u32, u32),
+    /// The version of the config file after this migration has been applied
+    to: (u32, u32),
+    /// The function that changes the contents of the config file
+    apply: fn(&mut DocumentMut),
+}
+
+/// All migrations known to this program, ordered from the oldest to the newest version.
+/// All versions of the config file so far can be read without a migration.
+const MIGRATIONS: [Migration; 0] = [];
+
+/// The errors that can occur while migrating a config file
+pub enum Error {
+    /// The version of the config file could not be parsed
+    InvalidVersion(String),
+    /// There is no chain of migrations from the version of the config file to a compatible version
+    NoMigration(String),
+    /// The config file is not valid TOML
+    Parse(String),
+    /// The config file is not written in TOML
+    Format,
+    /// The config file could not be read
+    Read(String, io::Error),
+    /// The migrated config file or its backup could not be written
+    Write(String, io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidVersion(version) => write!(f, "\"{}\" is not a valid version", version),
+            Error::NoMigration(version) => {
+                write!(f, "No migration is available for version {}", version)
+            }
+            Error::Parse(why) => write!(f, "Could not parse the config file: {}", why),
+            Error::Format => write!(f, "Only config files written in TOML can be migrated"),
+            Error::Read(file, why) => write!(f, "Could not read {}: {}", file, why),
+            Error::Write(file, why) => write!(f, "Could not write {}: {}", file, why),
+        }
+    }
+}
+
+/// Upgrades the contents of a config file step by step to a version compatible with this program.
+///
+/// # Arguments
+///
+/// * `config_content`: The contents of the config file to upgrade
+/// * `version`: The version of the config file, as found by [read_version]
+///
+/// returns: Result<String, Error>
+///
+/// # Examples
+///
+/// ```
+/// let migrated = match migrate("version = \"0.1\"\n", "0.1") {
+///     Ok(s) => s,
+///     Err(why) => panic!("Could not migrate config file: {}", why),
+/// };
+/// ```
+pub fn migrate(config_content: &str, version: &str) -> Result<String, Error> {
+    apply_migrations(&MIGRATIONS, config_content, version)
+}
+
+/// Applies a chain of migrations to the contents of a config file, until it has a version
+/// compatible with this program. See [migrate].
+///
+/// # Arguments
+///
+/// * `migrations`: The known migrations, ordered from the oldest to the newest version
+/// * `config_content`: The contents of the config file to upgrade
+/// * `version`: The version of the config file
+///
+/// returns: Result<String, Error>
+fn apply_migrations(
+    migrations: &[Migration],
+    config_content: &str,
+    version: &str,
+) -> Result<String, Error> {
+    let mut current = parse_version(version)?;
+    let mut document: DocumentMut = config_content
+        .parse()
+        .map_err(|why: toml_edit::TomlError| Error::Parse(why.to_string()))?;
+
+    while !is_compatible(current) {
+        let migration = match migrations.iter().find(|m| m.from == current) {
+            Some(m) => m,
+            None => return Err(Error::NoMigration(version.to_string())),
+        };
+        info!(
+            "Migrating config file from version {}.{} to {}.{}...",
+            migration.from.0, migration.from.1, migration.to.0, migration.to.1
+        );
+        (migration.apply)(&mut document);
+        set_version(&mut document, migration.to);
+        current = migration.to;
+    }
+
+    Ok(document.to_string())
+}
+
+/// Creates a unified diff between the old and the migrated config file. Tokens are redacted in
+/// both files, so the diff can be shown safely.
+///
+/// # Arguments
+///
+/// * `old`: The contents of the config file before the migration
+/// * `new`: The contents of the config file after the migration
+///
+/// returns: String
+pub fn diff(old: &str, new: &str) -> String {
+    let (old, new) = (secrets::redact(old), secrets::redact(new));
+    TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .header("config (old)", "config (migrated)")
+        .to_string()
+}
+
+/// Migrates the config file at `config_file` and writes a backup of the old file next to it.
+///
+/// If `dry_run` is set, the file is not touched and a diff of the changes is returned instead.
+///
+/// # Arguments
+///
+/// * `config_file`: The location of the config file to migrate
+/// * `dry_run`: If the changes should only be printed and not written
+///
+/// returns: Result<Option<String>, Error>: The diff, if this was a dry run.
+pub fn migrate_file(config_file: &str, dry_run: bool) -> Result<Option<String>, Error> {
+    if Format::from_path(config_file) != Format::Toml {
+        return Err(Error::Format);
+    }
+    let config_content =
+        fs::read_to_string(config_file).map_err(|why| Error::Read(config_file.to_string(), why))?;
+    let version = read_version(&Format::Toml.parse(&config_content).map_err(Error::Parse)?);
+    if check_version(&version) && !dry_run {
+        info!("Config file is already compatible. Nothing to migrate.");
+        return Ok(None);
+    }
+
+    let migrated = migrate(&config_content, &version)?;
+    if dry_run {
+        return Ok(Some(diff(&config_content, &migrated)));
+    }
+    write(config_file, &version, &migrated)?;
+    Ok(None)
+}
+
+/// Replaces a config file with its migrated contents and keeps the old file as a backup next to
+/// it. The backup gets the same permissions as the config file, as it contains the same secrets.
+///
+/// # Arguments
+///
+/// * `config_file`: The location of the config file
+/// * `version`: The version of the old config file, which is part of the name of the backup
+/// * `migrated`: The contents of the migrated config file
+///
+/// returns: Result<(), Error>
+pub fn write(config_file: &str, version: &str, migrated: &str) -> Result<(), Error> {
+    let backup_file = format!("{}.{}.bak", config_file, version);
+    fs::copy(config_file, &backup_file).map_err(|why| Error::Write(backup_file.clone(), why))?;
+    warn!("Written a backup of the old config file to {}", backup_file);
+    fs::write(config_file, migrated).map_err(|why| Error::Write(config_file.to_string(), why))?;
+    info!("Config file {} has been migrated!", config_file);
+    Ok(())
+}
+
+/// Checks if a version can be read by this program without any more migrations
+fn is_compatible(version: (u32, u32)) -> bool {
+    let program_version = (
+        env!("CARGO_PKG_VERSION_MAJOR").parse::<u32>().unwrap(),
+        env!("CARGO_PKG_VERSION_MINOR").parse::<u32>().unwrap(),
+    );
+    version == program_version || COMPATIBLE_VERSIONS.contains(&version)
+}
+
+/// Parses a version string like `0.3` into a tuple of major and minor version
+fn parse_version(version: &str) -> Result<(u32, u32), Error> {
+    let invalid = || Error::InvalidVersion(version.to_string());
+    let (major, minor) = version.split_once('.').ok_or_else(invalid)?;
+    Ok((
+        major.parse().map_err(|_| invalid())?,
+        minor.parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// Sets the version of the document, but keeps the comments around it.
+fn set_version(document: &mut DocumentMut, version: (u32, u32)) {
+    let version = format!("{}.{}", version.0, version.1);
+    match document.get_mut("version").and_then(Item::as_value_mut) {
+        Some(value) => {
+            let decor = value.decor().clone();
+            *value = version.into();
+            *value.decor_mut() = decor;
+        }
+        None => document["version"] = toml_edit::value(version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_parsed() {
+        let cases = [
+            ("0.3", Some((0, 3))),
+            ("0.2", Some((0, 2))),
+            ("1.12", Some((1, 12))),
+            ("", None),
+            ("0", None),
+            ("0.", None),
+            ("a.b", None),
+            ("0.3.1", None),
+            ("-1.0", None),
+        ];
+        for (version, expected) in cases {
+            assert_eq!(
+                parse_version(version).ok(),
+                expected,
+                "version {:?}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn compatible_versions_need_no_migration() {
+        assert!(is_compatible((0, 2)));
+        assert!(is_compatible((0, 3)));
+        assert!(!is_compatible((0, 1)));
+        assert!(!is_compatible((1, 0)));
+    }
+
+    #[test]
+    fn the_version_is_set_with_its_comments() {
+        let cases = [
+            (
+                "# My config\nversion = \"0.1\" # old\n",
+                "# My config\nversion = \"0.3\" # old\n",
+            ),
+            ("[kaenguru]\n", "version = \"0.3\"\n[kaenguru]\n"),
+        ];
+        for (content, expected) in cases {
+            let mut document: DocumentMut = content.parse().unwrap();
+            set_version(&mut document, (0, 3));
+            assert_eq!(document.to_string(), expected, "content {:?}", content);
+        }
+    }
+
+    #[test]
+    fn compatible_files_are_not_changed() {
+        let content = "# Comment\nversion = \"0.2\"\n\n[kaenguru]\ntoken = \"abc\" # Secret\n";
+        assert_eq!(migrate(content, "0.2").ok().as_deref(), Some(content));
+    }
+
+    #[test]
+    fn unknown_versions_are_not_migrated() {
+        assert!(matches!(
+            migrate("version = \"0.1\"\n", "0.1"),
+            Err(Error::NoMigration(version)) if version == "0.1"
+        ));
+        assert!(matches!(
+            migrate("version = \"x\"\n", "x"),
+            Err(Error::InvalidVersion(version)) if version == "x"
+        ));
+        assert!(matches!(migrate("version = ", "0.1"), Err(Error::Parse(_))));
+    }
+
+    /// Renames `reply` to `response_pool` in all responses
+    fn rename_replies(document: &mut DocumentMut) {
+        let responses = document["autokommentator"]["responses"].as_array_of_tables_mut();
+        for response in responses
+            .into_iter()
+            .flat_map(|responses| responses.iter_mut())
+        {
+            if let Some(reply) = response.remove("reply") {
+                response.insert("response_pool", reply);
+            }
+        }
+    }
+
+    /// Adds the Känguru Knecht, which became mandatory
+    fn add_kaenguru(document: &mut DocumentMut) {
+        document["kaenguru"] = toml_edit::table();
+    }
+
+    /// A made-up chain of migrations from version 0.0 to 0.2
+    const CHAIN: [Migration; 2] = [
+        Migration {
+            from: (0, 0),
+            to: (0, 1),
+            apply: rename_replies,
+        },
+        Migration {
+            from: (0, 1),
+            to: (0, 2),
+            apply: add_kaenguru,
+        },
+    ];
+
+    #[test]
+    fn migrations_are_applied_one_after_another() {
+        let content = r#"# My config
+version = "0.0" # Do not change
+
+[[autokommentator.responses]]
+trigger = ["xd"] # The trigger
+reply = ["XD"]
+"#;
+        let expected = r#"# My config
+version = "0.2" # Do not change
+
+[[autokommentator.responses]]
+trigger = ["xd"] # The trigger
+response_pool = ["XD"]
+
+[kaenguru]
+"#;
+        let migrated = apply_migrations(&CHAIN, content, "0.0").ok();
+        assert_eq!(migrated.as_deref(), Some(expected));
+
+        // A file in the middle of the chain only needs the last migration
+        let migrated = apply_migrations(&CHAIN, "version = \"0.1\"\n", "0.1").ok();
+        assert_eq!(
+            migrated.as_deref(),
+            Some("version = \"0.2\"\n\n[kaenguru]\n")
+        );
+
+        // There is no migration from 0.1 without the second step
+        assert!(matches!(
+            apply_migrations(&CHAIN[..1], "version = \"0.0\"\n", "0.0"),
+            Err(Error::NoMigration(version)) if version == "0.0"
+        ));
+    }
+
+    #[test]
+    fn the_old_file_is_kept_as_a_backup() {
+        let directory = std::env::temp_dir().join(format!("xd_bot_migrate_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let config_file = directory.join("config.toml");
+        let config_file = config_file.to_string_lossy().to_string();
+        fs::write(&config_file, "version = \"0.0\"\n").unwrap();
+
+        let result = write(&config_file, "0.0", "version = \"0.2\"\n");
+        let migrated = fs::read_to_string(&config_file).unwrap();
+        let backup = fs::read_to_string(format!("{}.0.0.bak", config_file)).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(migrated, "version = \"0.2\"\n");
+        assert_eq!(backup, "version = \"0.0\"\n");
+    }
+
+    #[test]
+    fn diffs_do_not_contain_tokens() {
+        let old = "version = \"0.1\"\n[kaenguru]\ntoken = \"secret\"\n";
+        let new = "version = \"0.3\"\n[kaenguru]\ntoken = \"secret\"\n";
+        let diff = diff(old, new);
+        assert!(diff.contains("-version = \"0.1\""), "{}", diff);
+        assert!(diff.contains("+version = \"0.3\""), "{}", diff);
+        assert!(!diff.contains("secret"), "{}", diff);
+    }
+
+    #[test]
+    fn only_toml_files_are_migrated() {
+        assert!(matches!(
+            migrate_file("config.yaml", false),
+            Err(Error::Format)
+        ));
+        assert!(matches!(
+            migrate_file("does/not/exist.toml", false),
+            Err(Error::Read(..))
+        ));
+    }
+}
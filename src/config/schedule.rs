@@ -0,0 +1,292 @@
+//!
+//! This module decides if a response is active at a given time.
+//!
+//! A [Schedule] can restrict a response to weekdays, months, days of the year and times of the
+//! day. All restrictions that are set have to be fulfilled, e.g.
+//! `{ weekdays = ["sat", "sun"], times = ["22:00-06:00"] }` is only active on weekend nights.
+//! Ranges of days and times can wrap around, like `"12-24..01-06"` or `"22:00-06:00"`.
+//!
+//! The time is evaluated in the timezone configured for the bot.
+//!
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields)]
+/// When a response is active. Restrictions that are not set (or empty) always match.
+pub struct Schedule {
+    /// The days of the week, e.g. `["sat", "sun"]`
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub weekdays: Vec<Weekday>,
+    /// The months, from `1` (January) to `12` (December)
+    #[serde(default)]
+    pub months: Vec<u32>,
+    /// Days (`"MM-DD"`) or ranges of days (`"MM-DD..MM-DD"`), e.g. `["10-03", "12-24..12-26"]`
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub dates: Vec<DateRange>,
+    /// Ranges of the time of the day (`"HH:MM-HH:MM"`), e.g. `["22:00-06:00"]`.
+    /// The end is not part of the range, so the start and the end must differ.
+    #[serde(default)]
+    #[schemars(with = "Vec<String>")]
+    pub times: Vec<TimeRange>,
+}
+
+/// A range of days of the year, which is written as `"MM-DD"` or `"MM-DD..MM-DD"`
+#[derive(Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(try_from = "String")]
+pub struct DateRange {
+    /// The first day of the range as (month, day)
+    start: (u32, u32),
+    /// The last day of the range as (month, day)
+    end: (u32, u32),
+}
+
+/// A range of the time of the day, which is written as `"HH:MM-HH:MM"`
+#[derive(Deserialize, Copy, Clone, Eq, PartialEq, Debug)]
+#[serde(try_from = "String")]
+pub struct TimeRange {
+    /// The start of the range
+    start: NaiveTime,
+    /// The end of the range, which is not part of it
+    end: NaiveTime,
+}
+
+impl Schedule {
+    /// Checks if this schedule is active at the given time.
+    ///
+    /// # Arguments
+    ///
+    /// * `now`: The current time in the timezone of the bot
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let schedule = Schedule {
+    ///     dates: vec!["10-03".to_string().try_into().unwrap()],
+    ///     ..Default::default()
+    /// };
+    /// let now = NaiveDate::from_ymd_opt(1990, 10, 3).unwrap().and_hms_opt(12, 0, 0).unwrap();
+    /// assert!(schedule.is_active(&now));
+    /// ```
+    pub fn is_active(&self, now: &NaiveDateTime) -> bool {
+        (self.weekdays.is_empty() || self.weekdays.contains(&now.weekday()))
+            && (self.months.is_empty() || self.months.contains(&now.month()))
+            && (self.dates.is_empty() || self.dates.iter().any(|dates| dates.contains(now)))
+            && (self.times.is_empty() || self.times.iter().any(|times| times.contains(now)))
+    }
+}
+
+impl DateRange {
+    /// Checks if the day of `now` is part of this range
+    fn contains(&self, now: &NaiveDateTime) -> bool {
+        let day = (now.month(), now.day());
+        if self.start <= self.end {
+            self.start <= day && day <= self.end
+        } else {
+            // The range wraps around the new year
+            self.start <= day || day <= self.end
+        }
+    }
+}
+
+impl TimeRange {
+    /// Checks if the time of `now` is part of this range
+    fn contains(&self, now: &NaiveDateTime) -> bool {
+        // Ignore seconds, so that "22:00-22:01" is active for the whole minute
+        let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap();
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            // The range wraps around midnight
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+impl TryFrom<String> for DateRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let parse = |date: &str| -> Result<(u32, u32), String> {
+            let invalid = || format!("\"{}\" is not a valid date (MM-DD)", date);
+            let (month, day) = date.trim().split_once('-').ok_or_else(invalid)?;
+            let month: u32 = month.parse().map_err(|_| invalid())?;
+            let day: u32 = day.parse().map_err(|_| invalid())?;
+            // 2000 is a leap year, so the 29th of February is valid as well
+            chrono::NaiveDate::from_ymd_opt(2000, month, day).ok_or_else(invalid)?;
+            Ok((month, day))
+        };
+        let (start, end) = match value.split_once("..") {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(&value)?, parse(&value)?),
+        };
+        Ok(DateRange { start, end })
+    }
+}
+
+impl TryFrom<String> for TimeRange {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("\"{}\" is not a valid time range (HH:MM-HH:MM)", value);
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M");
+        let range = TimeRange {
+            start: parse(start).map_err(|_| invalid())?,
+            end: parse(end).map_err(|_| invalid())?,
+        };
+        // The end is not part of the range, so it would never be active
+        if range.start == range.end {
+            return Err(format!(
+                "The time range \"{}\" is empty, as it ends when it starts. Leave out times to be active the whole day",
+                value
+            ));
+        }
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Creates a time in the year 2024
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 30)
+            .unwrap()
+    }
+
+    #[test]
+    fn time_ranges_are_parsed() {
+        let cases = [
+            ("22:00-06:00", true),
+            ("08:00-12:30", true),
+            (" 08:00 - 12:30 ", true),
+            ("00:00-23:59", true),
+            ("22:00-22:00", false),
+            ("22:00", false),
+            ("22-06", false),
+            ("25:00-06:00", false),
+            ("", false),
+        ];
+        for (value, valid) in cases {
+            assert_eq!(
+                TimeRange::try_from(value.to_string()).is_ok(),
+                valid,
+                "time range {:?}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn time_ranges_contain_their_start_but_not_their_end() {
+        let cases = [
+            ("08:00-12:00", (8, 0), true),
+            ("08:00-12:00", (11, 59), true),
+            ("08:00-12:00", (12, 0), false),
+            ("08:00-12:00", (7, 59), false),
+            ("22:00-22:01", (22, 0), true),
+            // Wrapping around midnight
+            ("22:00-06:00", (23, 30), true),
+            ("22:00-06:00", (0, 0), true),
+            ("22:00-06:00", (5, 59), true),
+            ("22:00-06:00", (6, 0), false),
+            ("22:00-06:00", (12, 0), false),
+        ];
+        for (value, (hour, minute), expected) in cases {
+            let range = TimeRange::try_from(value.to_string()).unwrap();
+            assert_eq!(
+                range.contains(&at(1, 1, hour, minute)),
+                expected,
+                "{:02}:{:02} in {:?}",
+                hour,
+                minute,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn date_ranges_are_parsed() {
+        let cases = [
+            ("10-03", true),
+            ("12-24..12-26", true),
+            ("12-24..01-06", true),
+            ("02-29", true),
+            ("02-30", false),
+            ("13-01", false),
+            ("10.03", false),
+            ("10-03..", false),
+            ("", false),
+        ];
+        for (value, valid) in cases {
+            assert_eq!(
+                DateRange::try_from(value.to_string()).is_ok(),
+                valid,
+                "date range {:?}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn date_ranges_contain_their_first_and_last_day() {
+        let cases = [
+            ("10-03", (10, 3), true),
+            ("10-03", (10, 4), false),
+            ("12-24..12-26", (12, 24), true),
+            ("12-24..12-26", (12, 26), true),
+            ("12-24..12-26", (12, 27), false),
+            // Wrapping around the new year
+            ("12-24..01-06", (12, 31), true),
+            ("12-24..01-06", (1, 6), true),
+            ("12-24..01-06", (1, 7), false),
+            ("12-24..01-06", (6, 1), false),
+        ];
+        for (value, (month, day), expected) in cases {
+            let range = DateRange::try_from(value.to_string()).unwrap();
+            assert_eq!(
+                range.contains(&at(month, day, 12, 0)),
+                expected,
+                "{:02}-{:02} in {:?}",
+                month,
+                day,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn all_restrictions_have_to_match() {
+        // Active on weekend nights in december
+        let schedule = Schedule {
+            weekdays: vec![Weekday::Sat, Weekday::Sun],
+            months: vec![12],
+            dates: Vec::new(),
+            times: vec![TimeRange::try_from("22:00-06:00".to_string()).unwrap()],
+        };
+        let cases = [
+            // Saturday, 7th of December
+            (at(12, 7, 23, 0), true),
+            (at(12, 7, 12, 0), false),
+            // Friday, 6th of December
+            (at(12, 6, 23, 0), false),
+            // Saturday, 9th of November
+            (at(11, 9, 23, 0), false),
+        ];
+        for (now, expected) in cases {
+            assert_eq!(schedule.is_active(&now), expected, "{}", now);
+        }
+        assert!(Schedule::default().is_active(&at(1, 1, 0, 0)));
+    }
+}
@@ -0,0 +1,169 @@
+//!
+//! This module contains the configuration of the messages, that the bots post on their own at
+//! scheduled times.
+//!
+//! The times are [cron expressions](https://en.wikipedia.org/wiki/Cron) with five fields
+//! (minute, hour, day of the month, month, day of the week), or six fields with the seconds in
+//! front, e.g. `"0 9 * * Mon"` for every monday at 9:00. Weekdays can also be numbers like in
+//! standard cron, from 0 (sunday) to 6 (saturday), 7 is sunday as well. Unlike standard cron,
+//! a day of the month and a day of the week both have to match, e.g. `"0 9 13 * Fri"` is only
+//! due on friday the 13th.
+//!
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+/// A message that a bot posts on its own at scheduled times
+pub struct ScheduledPost {
+    /// When the message is posted, as a cron expression, e.g. `"0 9 * * Mon"`. The time is
+    /// evaluated in the timezone of the bot.
+    #[schemars(with = "String")]
+    pub cron: CronSchedule,
+    /// The IDs of the channels in which the message is posted
+    pub channels: Vec<u64>,
+    /// The message to post
+    pub message: Option<String>,
+    /// A trigger of a response of the bot, whose response pool is used instead of `message`
+    pub response: Option<String>,
+    /// If a post, that was missed because the bot was not running, is made up when the bot
+    /// starts. Only the last missed post is made up. Default: false
+    #[serde(default)]
+    pub catch_up: bool,
+}
+
+/// A parsed cron expression
+#[derive(Deserialize, Clone, Debug)]
+#[serde(try_from = "String")]
+pub struct CronSchedule(pub cron::Schedule);
+
+impl TryFrom<String> for CronSchedule {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let mut fields: Vec<String> = value.split_whitespace().map(str::to_string).collect();
+        // The cron crate needs the seconds, which are usually left out
+        if fields.len() == 5 {
+            fields.insert(0, "0".to_string());
+        }
+        if let Some(weekdays) = fields.get_mut(5) {
+            *weekdays = name_weekdays(weekdays);
+        }
+        cron::Schedule::from_str(&fields.join(" "))
+            .map(CronSchedule)
+            .map_err(|why| format!("\"{}\" is not a valid cron expression: {}", value, why))
+    }
+}
+
+/// The names of the weekdays, by their numbers in standard cron
+const WEEKDAYS: [&str; 8] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Replaces the numbers in the weekday field of a cron expression with the names of the weekdays.
+/// Standard cron counts the weekdays from 0 (sunday) to 6 (saturday), but the cron crate counts
+/// them from 1 (sunday) to 7 (saturday).
+///
+/// # Arguments
+///
+/// * `field`: The weekday field, e.g. `"1-5"`
+///
+/// returns: String: The field with the names, e.g. `"Mon,Tue,Wed,Thu,Fri"`. Parts that are not
+/// numbers are kept, so the cron crate can parse or reject them.
+fn name_weekdays(field: &str) -> String {
+    field
+        .split(',')
+        .map(|part| {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<usize>().ok()),
+                None => (part, Some(1)),
+            };
+            let (start, end) = match range.split_once('-') {
+                Some((start, end)) => (start.parse::<usize>().ok(), end.parse::<usize>().ok()),
+                // "1/2" means every second day from monday on
+                None if part.contains('/') => (range.parse::<usize>().ok(), Some(6)),
+                None => (range.parse::<usize>().ok(), range.parse::<usize>().ok()),
+            };
+            match (start, end, step) {
+                (Some(start), Some(end), Some(step)) if start <= end && end <= 7 && step > 0 => {
+                    (start..=end)
+                        .step_by(step)
+                        .map(|day| WEEKDAYS[day])
+                        .collect::<Vec<&str>>()
+                        .join(",")
+                }
+                _ => part.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weekdays_are_numbered_like_standard_cron() {
+        let cases = [
+            ("*", "*"),
+            ("Mon", "Mon"),
+            ("Mon-Fri", "Mon-Fri"),
+            ("0", "Sun"),
+            ("1", "Mon"),
+            ("6", "Sat"),
+            ("7", "Sun"),
+            ("1-5", "Mon,Tue,Wed,Thu,Fri"),
+            ("5-7", "Fri,Sat,Sun"),
+            ("0,6", "Sun,Sat"),
+            ("1-5/2", "Mon,Wed,Fri"),
+            ("1/2", "Mon,Wed,Fri"),
+            ("*/2", "*/2"),
+            ("8", "8"),
+            ("5-1", "5-1"),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(name_weekdays(field), expected, "field {:?}", field);
+        }
+    }
+
+    #[test]
+    fn numeric_weekdays_fire_on_the_standard_day() {
+        use chrono::{Datelike, TimeZone, Utc, Weekday};
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        for (expression, weekday) in [
+            ("0 9 * * 1", Weekday::Mon),
+            ("0 9 * * 0", Weekday::Sun),
+            ("0 9 * * 7", Weekday::Sun),
+            ("0 9 * * 6", Weekday::Sat),
+            ("0 0 9 * * 5", Weekday::Fri),
+        ] {
+            let schedule = CronSchedule::try_from(expression.to_string()).unwrap();
+            let next = schedule.0.after(&start).next().unwrap();
+            assert_eq!(next.weekday(), weekday, "expression {:?}", expression);
+        }
+    }
+
+    #[test]
+    fn day_of_month_and_weekday_both_have_to_match() {
+        use chrono::{Datelike, TimeZone, Utc, Weekday};
+
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let schedule = CronSchedule::try_from("0 9 13 * Fri".to_string()).unwrap();
+        for next in schedule.0.after(&start).take(3) {
+            assert_eq!((next.day(), next.weekday()), (13, Weekday::Fri), "{}", next);
+        }
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        for expression in ["", "0 9 * *", "0 9 * * 8", "0 25 * * *", "nonsense"] {
+            assert!(
+                CronSchedule::try_from(expression.to_string()).is_err(),
+                "expression {:?}",
+                expression
+            );
+        }
+    }
+}
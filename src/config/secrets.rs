@@ -0,0 +1,271 @@
+//!
+//! This module handles secrets in the configuration:
+//!
+//! * [Secret] wraps values like tokens, so that they are never printed to the logs.
+//! * [interpolate] replaces `${VAR}` and `${VAR:-default}` in all strings of the config with
+//!   the value of the environment variable `VAR`.
+//! * [read_token] reads a token either from the config or from a file (e.g. a docker secret).
+//!
+
+use crate::regex;
+use log::debug;
+use serde::{Deserialize, Deserializer};
+use std::{env, fmt, fs};
+use toml::Value;
+
+/// The text that is printed instead of a secret
+const REDACTED: &str = "[redacted]";
+
+/// A value that must not be printed to the logs, for example a discord token.
+///
+/// Its [Debug](fmt::Debug) and [Display](fmt::Display) implementations only print `[redacted]`.
+/// To get the actual value, use [Secret::expose].
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Gets the actual value of the secret.
+    ///
+    /// **Never log the returned value!**
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Secret(String::deserialize(deserializer)?))
+    }
+}
+
+/// Gets the token of a bot, either directly from the config or from the file at `token_file`.
+///
+/// If both are set, `token` takes precedence.
+///
+/// # Arguments
+///
+/// * `token`: The token as configured in the config file
+/// * `token_file`: The location of a file that only contains the token, e.g. `/run/secrets/xd`
+///
+/// returns: Result<Option<Secret>, String>
+pub fn read_token(
+    token: &Option<Secret>,
+    token_file: &Option<String>,
+) -> Result<Option<Secret>, String> {
+    if let Some(token) = token {
+        return Ok(Some(token.clone()));
+    }
+    match token_file {
+        Some(file) => {
+            debug!("Reading token from file {}", file);
+            match fs::read_to_string(file) {
+                Ok(s) => Ok(Some(Secret(s.trim().to_string()))),
+                Err(why) => Err(format!("Could not read token file {}: {}", file, why)),
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// Replaces all environment variables in the strings of a config value.
+///
+/// The following forms are supported:
+///
+/// * `${VAR}`: Replaced with the value of `VAR`. It is an error if `VAR` is not set.
+/// * `${VAR:-default}`: Replaced with the value of `VAR` or `default`, if `VAR` is not set or empty.
+/// * `$${VAR}`: Escapes the variable and results in the literal text `${VAR}`.
+///
+/// # Arguments
+///
+/// * `value`: The value in which all strings should be interpolated
+///
+/// returns: Result<(), String>
+///
+/// # Examples
+///
+/// ```
+/// let mut value: toml::Value = toml::from_str("token = \"${DISCORD_TOKEN}\"").unwrap();
+/// interpolate(&mut value)?;
+/// ```
+pub fn interpolate(value: &mut Value) -> Result<(), String> {
+    match value {
+        Value::String(s) => *s = interpolate_str(s)?,
+        Value::Array(array) => {
+            for item in array {
+                interpolate(item)?;
+            }
+        }
+        Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                interpolate(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replaces all environment variables in a single string. See [interpolate].
+pub fn interpolate_str(input: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(input.len());
+    let mut last = 0;
+
+    for captures in
+        regex!(r"\$(\$)?\{([A-Za-z_][A-Za-z0-9_]*)(?::-([^}]*))?\}").captures_iter(input)
+    {
+        let whole = captures.get(0).unwrap();
+        out.push_str(&input[last..whole.start()]);
+        last = whole.end();
+
+        // Escaped with $$
+        if captures.get(1).is_some() {
+            out.push_str(&whole.as_str()[1..]);
+            continue;
+        }
+
+        let name = captures.get(2).unwrap().as_str();
+        match (env::var(name), captures.get(3)) {
+            (Ok(value), Some(default)) if value.is_empty() => out.push_str(default.as_str()),
+            (Ok(value), _) => out.push_str(&value),
+            (Err(_), Some(default)) => out.push_str(default.as_str()),
+            (Err(_), None) => {
+                return Err(format!(
+                    "The environment variable {} is used in the config file, but it is not set",
+                    name
+                ))
+            }
+        }
+    }
+    out.push_str(&input[last..]);
+
+    Ok(out)
+}
+
+/// Removes the values of all tokens from the contents of a config file, so it can be logged.
+///
+/// # Arguments
+///
+/// * `config_content`: The contents of the config file
+///
+/// returns: String
+pub fn redact(config_content: &str) -> String {
+    regex!(r#"(?m)^(\s*"?token"?\s*[=:]\s*).*$"#)
+        .replace_all(config_content, format!("${{1}}\"{}\"", REDACTED))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_variables_are_replaced() {
+        env::set_var("XD_BOT_TEST_TOKEN", "abc");
+        env::set_var("XD_BOT_TEST_EMPTY", "");
+        env::remove_var("XD_BOT_TEST_UNSET");
+        let cases = [
+            ("${XD_BOT_TEST_TOKEN}", "abc"),
+            ("Bot ${XD_BOT_TEST_TOKEN}!", "Bot abc!"),
+            ("${XD_BOT_TEST_TOKEN}${XD_BOT_TEST_TOKEN}", "abcabc"),
+            ("${XD_BOT_TEST_TOKEN:-default}", "abc"),
+            ("${XD_BOT_TEST_UNSET:-default}", "default"),
+            ("${XD_BOT_TEST_UNSET:-}", ""),
+            ("${XD_BOT_TEST_EMPTY:-default}", "default"),
+            ("${XD_BOT_TEST_EMPTY}", ""),
+            ("$${XD_BOT_TEST_UNSET}", "${XD_BOT_TEST_UNSET}"),
+            (
+                "$${XD_BOT_TEST_TOKEN} ${XD_BOT_TEST_TOKEN}",
+                "${XD_BOT_TEST_TOKEN} abc",
+            ),
+            ("kein ${ Platzhalter", "kein ${ Platzhalter"),
+            ("$XD_BOT_TEST_TOKEN", "$XD_BOT_TEST_TOKEN"),
+            ("0.3", "0.3"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                interpolate_str(input).as_deref(),
+                Ok(expected),
+                "input {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn unset_variables_are_an_error() {
+        env::remove_var("XD_BOT_TEST_MISSING");
+        let error = interpolate_str("token = ${XD_BOT_TEST_MISSING}").unwrap_err();
+        assert!(error.contains("XD_BOT_TEST_MISSING"), "{}", error);
+    }
+
+    #[test]
+    fn all_strings_of_a_value_are_replaced() {
+        env::set_var("XD_BOT_TEST_CHANNEL", "general");
+        let mut value: Value = toml::from_str(
+            r##"
+            name = "${XD_BOT_TEST_CHANNEL}"
+            number = 5
+            list = ["${XD_BOT_TEST_CHANNEL:-x}", "b"]
+            [table]
+            nested = "#${XD_BOT_TEST_CHANNEL}"
+            "##,
+        )
+        .unwrap();
+        interpolate(&mut value).unwrap();
+        let expected: Value = toml::from_str(
+            r##"
+            name = "general"
+            number = 5
+            list = ["general", "b"]
+            [table]
+            nested = "#general"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn tokens_are_redacted() {
+        let cases = [
+            ("token = \"abc\"", "token = \"[redacted]\""),
+            ("  token=\"abc\" # comment", "  token=\"[redacted]\""),
+            ("\"token\": \"abc\",", "\"token\": \"[redacted]\""),
+            ("token: abc", "token: \"[redacted]\""),
+            (
+                "token_file = \"/run/secrets/xd\"",
+                "token_file = \"/run/secrets/xd\"",
+            ),
+            ("message = \"token = abc\"", "message = \"token = abc\""),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(redact(input), expected, "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn secrets_are_not_printed() {
+        let secret = Secret::from("abc".to_string());
+        assert_eq!(format!("{}", secret), "[redacted]");
+        assert_eq!(format!("{:?}", secret), "[redacted]");
+        assert_eq!(secret.expose(), "abc");
+    }
+}
@@ -0,0 +1,279 @@
+//!
+//! This module checks the parsed configuration for mistakes, which can not be caught by
+//! deserializing it. Every message contains the file the mistake was found in.
+//!
+
+use crate::config::{scheduled::ScheduledPost, Config, MatchPolicy, Reply, Response, SequenceStep};
+use crate::matcher::Searchable;
+use crate::normalize::{Normalizer, Step};
+use log::warn;
+use std::collections::HashMap;
+
+/// Checks the configuration for mistakes.
+///
+/// Triggers that are configured multiple times for the same bot in different files are an error.
+/// Within the same file, they only produce a warning under the first-match policy, as only the
+/// first one will ever be used. The other policies can use both responses.
+///
+/// # Arguments
+///
+/// * `config`: The parsed configuration to check
+///
+/// returns: Vec<String>: All errors that were found. If it is empty, the configuration is valid.
+pub fn validate(config: &Config) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (bot, chance, human_delay) in [
+        (
+            "autokommentator",
+            config.autokommentator.chance,
+            config.autokommentator.human_delay,
+        ),
+        (
+            "kaenguru",
+            config.kaenguru.chance,
+            config.kaenguru.human_delay,
+        ),
+    ] {
+        if !is_probability(chance) {
+            errors.push(format!(
+                "The chance {} of the {} has to be between 0.0 and 1.0",
+                chance, bot
+            ));
+        }
+        if let Some(human_delay) = human_delay {
+            if ![human_delay.seconds_per_character, human_delay.max]
+                .into_iter()
+                .all(is_positive_seconds)
+            {
+                errors.push(format!(
+                    "The seconds of the human_delay of the {} must not be negative",
+                    bot
+                ));
+            }
+            if !is_probability(human_delay.jitter) {
+                errors.push(format!(
+                    "The jitter of the human_delay of the {} has to be between 0.0 and 1.0",
+                    bot
+                ));
+            }
+        }
+    }
+    if !config
+        .kaenguru
+        .embed_delete_after
+        .is_none_or(is_positive_seconds)
+    {
+        errors.push("The embed_delete_after of the kaenguru must not be negative".to_string());
+    }
+    if config.kaenguru.max_conversions == 0 {
+        errors.push("The max_conversions of the kaenguru have to be at least 1".to_string());
+    }
+    if config.kaenguru.quotes.daily_hour > 23 {
+        errors.push("The daily_hour of the quotes has to be between 0 and 23".to_string());
+    }
+    for converter in &config.kaenguru.converters {
+        if !converter.factor.is_finite() {
+            errors.push(format!(
+                "The factor of the converter {} has to be a number",
+                converter.pattern.0
+            ));
+        }
+    }
+    for (currency, rate) in &config.kaenguru.exchange_rates {
+        if !rate.is_finite() || *rate <= 0.0 {
+            errors.push(format!(
+                "The exchange rate of {} has to be a positive number",
+                currency
+            ));
+        }
+    }
+    validate_responses(
+        "autokommentator",
+        &config.autokommentator.responses,
+        &config.autokommentator.normalize,
+        config.autokommentator.match_policy,
+        &mut errors,
+    );
+    validate_responses(
+        "kaenguru",
+        &config.kaenguru.responses,
+        &config.kaenguru.normalize,
+        config.kaenguru.match_policy,
+        &mut errors,
+    );
+    validate_scheduled(
+        "autokommentator",
+        &config.autokommentator.scheduled,
+        &config.autokommentator.responses,
+        &mut errors,
+    );
+    validate_scheduled(
+        "kaenguru",
+        &config.kaenguru.scheduled,
+        &config.kaenguru.responses,
+        &mut errors,
+    );
+    errors
+}
+
+/// Checks the scheduled posts of a single bot.
+fn validate_scheduled(
+    bot: &str,
+    posts: &[ScheduledPost],
+    responses: &[Response],
+    errors: &mut Vec<String>,
+) {
+    for post in posts {
+        if post.channels.is_empty() {
+            errors.push(format!(
+                "The scheduled post \"{}\" of the {} has no channels",
+                post.cron.0, bot
+            ));
+        }
+        match (&post.message, &post.response) {
+            (Some(_), None) => {}
+            (None, Some(trigger)) => {
+                if !responses
+                    .iter()
+                    .any(|response| response.triggers().contains(trigger))
+                {
+                    errors.push(format!(
+                        "The {} has no response with the trigger \"{}\" for the scheduled post \"{}\"",
+                        bot, trigger, post.cron.0
+                    ));
+                }
+            }
+            _ => errors.push(format!(
+                "The scheduled post \"{}\" of the {} needs either a message or a response",
+                post.cron.0, bot
+            )),
+        }
+    }
+}
+
+/// Checks the responses of a single bot.
+///
+/// Triggers that are empty after they were normalized are skipped by the [Matcher](crate::matcher::Matcher), so they only
+/// produce a warning.
+fn validate_responses(
+    bot: &str,
+    responses: &[Response],
+    normalize: &[Step],
+    match_policy: MatchPolicy,
+    errors: &mut Vec<String>,
+) {
+    // The lowercase trigger and the file it was first found in
+    let mut triggers: HashMap<String, &str> = HashMap::new();
+
+    for response in responses {
+        if response.trigger.is_empty() {
+            errors.push(format!(
+                "{}: A response of the {} has no triggers",
+                response.origin, bot
+            ));
+        }
+        if response.response_pool.is_empty() {
+            errors.push(format!(
+                "{}: A response of the {} has an empty response_pool",
+                response.origin, bot
+            ));
+        }
+        if let Some(chance) = response.chance.filter(|chance| !is_probability(*chance)) {
+            errors.push(format!(
+                "{}: The chance {} of a response of the {} has to be between 0.0 and 1.0",
+                response.origin, chance, bot
+            ));
+        }
+        if let Some(month) = response
+            .active
+            .iter()
+            .flat_map(|schedule| &schedule.months)
+            .find(|month| !(1..=12).contains(*month))
+        {
+            errors.push(format!(
+                "{}: The month {} of a response of the {} has to be between 1 and 12",
+                response.origin, month, bot
+            ));
+        }
+        if !response.delete_after.is_none_or(is_positive_seconds) {
+            errors.push(format!(
+                "{}: The delete_after of a response of the {} must not be negative",
+                response.origin, bot
+            ));
+        }
+        for reply in &response.response_pool {
+            match reply.clone().try_into::<Reply>() {
+                Err(_) => errors.push(format!(
+                    "{}: The response {} of the {} is not a string, a sequence of messages or a generator",
+                    response.origin, reply, bot
+                )),
+                Ok(Reply::Sequence(steps)) if steps.is_empty() => errors.push(format!(
+                    "{}: A response of the {} is an empty sequence",
+                    response.origin, bot
+                )),
+                Ok(Reply::Sequence(steps)) => {
+                    for step in steps {
+                        if let SequenceStep::Delayed { delay, .. } = step {
+                            if !is_positive_seconds(delay) {
+                                errors.push(format!(
+                                    "{}: The delay {} of a response of the {} must not be negative",
+                                    response.origin, delay, bot
+                                ));
+                            }
+                        }
+                    }
+                }
+                Ok(Reply::Generated { max_words: 0, .. }) => errors.push(format!(
+                    "{}: A generated response of the {} has to have at least 1 word",
+                    response.origin, bot
+                )),
+                Ok(Reply::Message(_)) | Ok(Reply::Generated { .. }) => {}
+            }
+        }
+
+        let normalizer = Normalizer::new(response.normalize.as_deref().unwrap_or(normalize));
+        for trigger in &response.trigger {
+            let trigger = match trigger.as_str() {
+                Some(s) if normalizer.normalize(s).is_empty() => {
+                    warn!(
+                        "{}: The trigger \"{}\" of the {} is empty and will never be found",
+                        response.origin, s, bot
+                    );
+                    continue;
+                }
+                Some(s) => s.to_lowercase(),
+                None => {
+                    errors.push(format!(
+                        "{}: The trigger {} of the {} is not a string",
+                        response.origin, trigger, bot
+                    ));
+                    continue;
+                }
+            };
+            match triggers.get(&trigger) {
+                Some(first) if *first != response.origin => errors.push(format!(
+                    "{}: The trigger \"{}\" of the {} is already defined in {}",
+                    response.origin, trigger, bot, first
+                )),
+                Some(first) if match_policy == MatchPolicy::FirstMatch => warn!(
+                    "{}: The trigger \"{}\" of the {} is already defined in {} and will never be used here",
+                    response.origin, trigger, bot, first
+                ),
+                Some(_) => {}
+                None => {
+                    triggers.insert(trigger, &response.origin);
+                }
+            }
+        }
+    }
+}
+
+/// Checks that a chance is between 0.0 and 1.0 (and not NaN)
+fn is_probability(chance: f64) -> bool {
+    (0.0..=1.0).contains(&chance)
+}
+
+/// Checks that a number of seconds is finite and not negative
+fn is_positive_seconds(seconds: f64) -> bool {
+    seconds >= 0.0 && seconds.is_finite()
+}
@@ -1,15 +1,30 @@
+mod converters;
 mod euro_to_mark;
+mod historical;
+mod number_words;
 
-use crate::config::Bots;
-use crate::kaenguru::euro_to_mark::get_euro;
-use crate::replies::reply_to;
+use crate::config::{Bots, ConversionMessages, CONFIG};
+use crate::kaenguru::converters::convert_units;
+use crate::kaenguru::euro_to_mark::{
+    get_amounts, mentions_currency, Amount, Currency, Parsed, THRESHOLD,
+};
+use crate::kaenguru::historical::{convert, find_year, format_money, Conversion};
+use crate::privacy;
+use crate::quotes;
+use crate::replies::{
+    cleanup, delete_replies,
+    history::{SentReplies, HISTORY},
+    remove_replies, reply_to, reply_to_edit, ReplyError,
+};
+use crate::scheduler;
 use log::{debug, error, info, trace};
 use serenity::{
     async_trait,
-    builder::{CreateAllowedMentions, CreateEmbed, CreateEmbedFooter, CreateMessage},
+    builder::{CreateAllowedMentions, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMessage},
     model::{channel::Message, gateway::Ready, prelude::*},
     prelude::*,
 };
+use std::{collections::BTreeMap, time::Duration};
 
 /// The default struct on which the bot is built
 pub struct KaenguruHandler;
@@ -29,84 +44,119 @@ impl EventHandler for KaenguruHandler {
             return;
         }
 
-        if let Ok(_) = reply_to(&ctx, &new_message, Bots::KaenguruKnecht).await {
+        match reply_to(&ctx, &new_message, Bots::KaenguruKnecht).await {
+            Ok(_) => return,
+            Err(ReplyError::NoReplyFound) => {}
+            Err(why) => debug!("Could not reply to message {}: {}", new_message.id, why),
+        }
+
+        if privacy::is_ignored(new_message.author.id, new_message.guild_id) {
             return;
         }
+        send_conversion(&ctx, &new_message).await;
+    }
+
+    /// The method that reacts to edited messages.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which this message was edited.
+    /// * `new`: The edited message, if it was in the cache.
+    /// * `event`: The changes that were made to the message.
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        match react_to_edit(&ctx, new, &event).await {
+            Ok(_) | Err(ReplyError::NoReplyFound) => {}
+            Err(why) => debug!(
+                "Could not react to the edit of message {}: {}",
+                event.id, why
+            ),
+        }
+    }
 
-        trace!("Checking for any amount of euros in the message...");
-        // Check if a € symbol or EUR is in the message, if so try to parse the cash amount
-        if new_message.content.to_lowercase().contains("€")
-            || new_message.content.to_lowercase().contains("eur")
+    /// The method that reacts to deleted messages.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which this message was deleted.
+    /// * `channel_id`: The channel in which the message was deleted.
+    /// * `deleted_message_id`: The message that was deleted.
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        if let Err(why) =
+            delete_replies(&ctx, channel_id, deleted_message_id, Bots::KaenguruKnecht).await
         {
-            let number = get_euro(&new_message.content.to_lowercase());
+            debug!(
+                "Could not delete the replies to message {}: {}",
+                deleted_message_id, why
+            );
+        }
+    }
 
-            // Check if a number was present in the message
-            if let Ok(number) = number {
-                if number == 0 {
-                    debug!("Message did not contain a number to convert to EUROs. Returning.");
-                    return;
-                }
-                let description = match number > 100_000 {
-                    // If the number is bigger than 100,000 send an "Error" message
-                    true => format!("Huiuiui! So viele Schulden kann die DDR doch nicht haben!"),
-                    // If the number is smaller than 100,000 send a computed message.
-                    false => {
-                        // If the number is also smaller than 10, append "Kleinvieh macht auch
-                        // Mist!" to the message
-                        match number < 10 {
-                            true => format!(
-                                "{} Euro? Das, das sind ja {} Mark! {} Ostmark! {} Ostmark aufm Schwarzmarkt!\n\nKleinvieh macht auch Mist!",
-                                number,
-                                number * 2,
-                                number * 4,
-                                number * 8
-                            ),
-                            false => format!(
-                                "{} Euro? Das, das sind ja {} Mark! {} Ostmark! {} Ostmark aufm Schwarzmarkt!",
-                                number,
-                                number * 2,
-                                number * 4,
-                                number * 8
-                            ),
-                        }
-                    }
-                };
-
-                let mut embed = CreateEmbed::new()
-                    // TODO add Author to the bot instance
-                    // Set the description of the description of above
-                    .description(&description)
-                    // Set the footer to "War ich ein guter Rechenknecht"?
-                    .footer(CreateEmbedFooter::new("War ich ein guter Rechenknecht?"));
-                // change the color to red if the number is bigger than 100,000
-                if number > 100_000 {
-                    embed = embed.color(Color::from_rgb(255, 0, 0));
-                }
+    /// The method that reacts to multiple messages being deleted at once.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which the messages were deleted.
+    /// * `channel_id`: The channel in which the messages were deleted.
+    /// * `multiple_deleted_messages_ids`: The messages that were deleted.
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        _guild_id: Option<GuildId>,
+    ) {
+        for message in multiple_deleted_messages_ids {
+            if let Err(why) = delete_replies(&ctx, channel_id, message, Bots::KaenguruKnecht).await
+            {
+                debug!(
+                    "Could not delete the replies to message {}: {}",
+                    message, why
+                );
+            }
+        }
+    }
 
-                // Send a reply message as an embed
-                match new_message
-                    .channel_id
-                    .send_message(
-                        &ctx.http,
-                        CreateMessage::new()
-                            .embed(embed)
-                            // References the original message
-                            .reference_message(&new_message)
-                            // Need to set this to false, because it would otherwise change the message
-                            // background yellow (for the user who wrote it).
-                            .allowed_mentions(CreateAllowedMentions::new().replied_user(false)),
-                    )
-                    .await
-                {
-                    // TODO add channel name (utils::get_channel function)
-                    Ok(msg) => info!(
-                        "Sending \"{}\" + embed to {}",
-                        description.replace("\n", "\\n"),
-                        msg.channel_id
-                    ),
-                    Err(why) => error!("Error sending message: {:?}", why),
-                };
-            };
+    /// The method that reacts to new reactions on messages.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which the reaction was added.
+    /// * `add_reaction`: The reaction that was added.
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        cleanup::delete_on_reaction(&ctx, &add_reaction, Bots::KaenguruKnecht).await;
+    }
+
+    /// The method that reacts to slash commands.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which the command was used.
+    /// * `interaction`: The interaction with the command.
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Command(command) = interaction {
+            if !privacy::run(&ctx, &command).await
+                && !quotes::run(&ctx, &command).await
+                && !scheduler::run_command(&ctx, &command, Bots::KaenguruKnecht).await
+            {
+                debug!("Unknown command: {}", command.data.name);
+            }
         }
     }
 
@@ -118,9 +168,20 @@ impl EventHandler for KaenguruHandler {
     /// * `_data_about_bot`: Some normal data about the newly created instance
     ///
     /// returns: ()
-    async fn ready(&self, _ctx: Context, data_about_bot: Ready) {
+    async fn ready(&self, ctx: Context, data_about_bot: Ready) {
         info!("Logged in as {}", data_about_bot.user.name);
 
+        let commands = privacy::commands()
+            .into_iter()
+            .chain(quotes::commands())
+            .chain(scheduler::commands())
+            .collect();
+        match Command::set_global_commands(&ctx.http, commands).await {
+            Ok(commands) => debug!("Registered {} slash commands", commands.len()),
+            Err(why) => error!("Could not register the slash commands: {:?}", why),
+        }
+        quotes::start_daily(&ctx);
+
         //TODO log invite links
         //        let permissions = Permissions::default();
         //        match data_about_bot.user.invite_url(&ctx, permissions).await {
@@ -129,3 +190,451 @@ impl EventHandler for KaenguruHandler {
         //        };
     }
 }
+
+/// Searches a message for amounts of money and other units and converts them.
+///
+/// # Arguments
+///
+/// * `message`: The message to search
+///
+/// returns: Option<(String, CreateEmbed)>: The text of the conversions and the embed that shows
+/// it, or None if there is nothing to convert in the message.
+fn convert_message(message: &Message) -> Option<(String, CreateEmbed)> {
+    trace!("Checking for any amount of money in the message...");
+    let content = message.content.to_lowercase();
+    let (exchange_rates, historical, max_conversions, messages, converters) = match CONFIG.lock() {
+        Ok(config) => (
+            config.kaenguru.exchange_rates.clone(),
+            config.kaenguru.historical,
+            config.kaenguru.max_conversions,
+            config.kaenguru.conversion_messages.clone(),
+            config.kaenguru.converters.clone(),
+        ),
+        Err(why) => panic!(
+            "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+            why, why
+        ),
+    };
+    let amounts = find_amounts(&content, &exchange_rates);
+
+    // The converted amounts, and if they are small enough for "Kleinvieh macht auch Mist!"
+    let mut conversions: Vec<(String, u64, bool)> = if amounts.is_empty() {
+        Vec::new()
+    } else if amounts.len() > 1 && (content.contains("zusammen") || content.contains("insgesamt")) {
+        // Sum all amounts up, if the user asks for it
+        let (sum, debt) = sum(&amounts);
+        let total = match debt {
+            true => format!("Zusammen {} Euro Schulden?", sum),
+            false => format!("Zusammen {} Euro?", sum),
+        };
+        vec![(describe(total, sum, None, &messages), sum, sum < 10)]
+    } else {
+        amounts
+            .iter()
+            .take(max_conversions)
+            .map(|(amount, number, too_big)| match too_big {
+                false => {
+                    // In the historical mode, convert to the currency of the year in the message
+                    let conversion = match historical {
+                        true => find_year(&content, &amount.span)
+                            .and_then(|year| convert(*number, year)),
+                        false => None,
+                    };
+                    let small = *number < 10 && conversion.is_none();
+                    (
+                        describe(original(amount, *number), *number, conversion, &messages),
+                        *number,
+                        small,
+                    )
+                }
+                // Amounts that are too big are always shown as too big, even if they would not
+                // be in euros
+                true => (messages.too_big.clone(), amount.value, false),
+            })
+            .collect()
+    };
+    // Add the jokes of the other units
+    conversions.extend(
+        convert_units(&message.content, &converters)
+            .into_iter()
+            .map(|answer| (answer, 0, false)),
+    );
+    conversions.truncate(max_conversions);
+
+    if conversions.is_empty() {
+        // Answer messages which mention a currency without an amount, if configured
+        match messages.not_found {
+            Some(ref not_found) if mentions_currency(&content) => {
+                conversions.push((not_found.clone(), 0, false))
+            }
+            _ => {
+                debug!("Message did not contain anything to convert.");
+                return None;
+            }
+        }
+    }
+
+    let mut description = conversions
+        .iter()
+        .map(|(text, _, _)| text.as_str())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    // If a number is also smaller than 10, append "Kleinvieh macht auch Mist!" to the message
+    if conversions.iter().any(|(_, _, small)| *small) {
+        description.push_str("\n\nKleinvieh macht auch Mist!");
+    }
+
+    let mut embed = CreateEmbed::new()
+        // TODO add Author to the bot instance
+        // Set the description of the description of above
+        .description(&description)
+        // Set the footer to "War ich ein guter Rechenknecht"?
+        .footer(CreateEmbedFooter::new("War ich ein guter Rechenknecht?"));
+    // change the color to red if a number is bigger than 100,000
+    if conversions.iter().any(|(_, number, _)| *number > THRESHOLD) {
+        embed = embed.color(Color::from_rgb(255, 0, 0));
+    }
+    Some((description, embed))
+}
+
+/// Sends the conversions of the amounts in a message as an embed and remembers it, so that it can
+/// be edited or deleted together with the message.
+///
+/// # Arguments
+///
+/// * `ctx`: The context in which the bot operates.
+/// * `message`: The message to reply to.
+///
+/// returns: ()
+async fn send_conversion(ctx: &Context, message: &Message) {
+    let (description, embed) = match convert_message(message) {
+        Some(conversion) => conversion,
+        None => return,
+    };
+
+    // Send a reply message as an embed
+    match message
+        .channel_id
+        .send_message(
+            &ctx.http,
+            CreateMessage::new()
+                .embed(embed)
+                // References the original message
+                .reference_message(message)
+                // Need to set this to false, because it would otherwise change the message
+                // background yellow (for the user who wrote it).
+                .allowed_mentions(CreateAllowedMentions::new().replied_user(false)),
+        )
+        .await
+    {
+        // TODO add channel name (utils::get_channel function)
+        Ok(msg) => {
+            info!(
+                "Sending \"{}\" + embed to {}",
+                description.replace("\n", "\\n"),
+                msg.channel_id
+            );
+            let (delete_after, delete_reaction, remember) = match CONFIG.lock() {
+                Ok(config) => (
+                    config.kaenguru.embed_delete_after,
+                    config.kaenguru.delete_reaction,
+                    config.kaenguru.reply_to_edits || config.kaenguru.delete_orphaned_replies,
+                ),
+                Err(why) => panic!(
+                    "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                    why, why
+                ),
+            };
+            cleanup::manage(
+                ctx,
+                &msg,
+                message.author.id,
+                Bots::KaenguruKnecht,
+                delete_after.map(Duration::from_secs_f64),
+                delete_reaction,
+            )
+            .await;
+            if remember {
+                HISTORY.lock().unwrap().insert(
+                    Bots::KaenguruKnecht,
+                    message.id,
+                    SentReplies {
+                        author: message.author.id,
+                        messages: vec![msg.id],
+                        triggers: Vec::new(),
+                        conversion: Some(description),
+                    },
+                );
+            }
+        }
+        Err(why) => error!("Error sending message: {:?}", why),
+    };
+}
+
+/// Reacts to an edited message, if the bot is configured to do so.
+///
+/// If the bot has sent conversions for the message, the embed is edited to match the new amounts
+/// in the message, or deleted if there is nothing left to convert. Replies to triggers are
+/// handled like in every bot (see [reply_to_edit]). If the bot has not reacted to the message
+/// at all afterwards, the amounts in it are converted like in a new message.
+///
+/// # Arguments
+///
+/// * `ctx`: The context in which the bot operates.
+/// * `new`: The edited message, if it is in the cache.
+/// * `event`: The event that contains the changes to the message.
+///
+/// returns: Result<(), ReplyError>
+async fn react_to_edit(
+    ctx: &Context,
+    new: Option<Message>,
+    event: &MessageUpdateEvent,
+) -> Result<(), ReplyError> {
+    let reply_to_edits = match CONFIG.lock() {
+        Ok(config) => config.kaenguru.reply_to_edits,
+        Err(why) => panic!(
+            "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+            why, why
+        ),
+    };
+    // Edits that do not change the content, e.g. embeds being loaded, are ignored
+    if !reply_to_edits || event.content.is_none() {
+        return Ok(());
+    }
+    let message = match new {
+        Some(message) => message,
+        None => event
+            .channel_id
+            .message(&ctx, event.id)
+            .await
+            .map_err(ReplyError::Fetching)?,
+    };
+    if message.author.bot || privacy::is_ignored(message.author.id, message.guild_id) {
+        return Ok(());
+    }
+
+    let previous = HISTORY
+        .lock()
+        .unwrap()
+        .get(Bots::KaenguruKnecht, message.id)
+        .cloned();
+    if let Some(SentReplies {
+        messages,
+        conversion: Some(previous),
+        ..
+    }) = previous
+    {
+        match convert_message(&message) {
+            Some((description, _)) if description == previous => {
+                trace!("The conversions of message {} did not change", message.id);
+                return Ok(());
+            }
+            Some((description, embed)) => {
+                let edited = message
+                    .channel_id
+                    .edit_message(&ctx, messages[0], EditMessage::new().embed(embed))
+                    .await
+                    .map_err(ReplyError::Editing)?;
+                info!(
+                    "Edited embed {} to \"{}\" in channel {}",
+                    edited.id,
+                    description.replace("\n", "\\n"),
+                    edited.channel_id
+                );
+                HISTORY.lock().unwrap().insert(
+                    Bots::KaenguruKnecht,
+                    message.id,
+                    SentReplies {
+                        author: message.author.id,
+                        messages,
+                        triggers: Vec::new(),
+                        conversion: Some(description),
+                    },
+                );
+                return Ok(());
+            }
+            None => {
+                debug!("Message {} does not contain amounts anymore", message.id);
+                remove_replies(ctx, message.channel_id, message.id, Bots::KaenguruKnecht).await?;
+            }
+        }
+    }
+
+    let result = reply_to_edit(ctx, Some(message.clone()), event, Bots::KaenguruKnecht).await;
+    let reacted = HISTORY
+        .lock()
+        .unwrap()
+        .get(Bots::KaenguruKnecht, message.id)
+        .is_some();
+    if !reacted {
+        send_conversion(ctx, &message).await;
+    }
+    result
+}
+
+/// Finds all amounts of money in a message and converts them to euros. Amounts without an
+/// exchange rate are left out, unless they are too big anyway.
+///
+/// # Arguments
+///
+/// * `content`: The content of the message
+/// * `exchange_rates`: The value of one unit of the other currencies in euros
+///
+/// returns: Vec<(Amount, u64, bool)>: The amounts, their value in euros and if they are too big
+fn find_amounts(content: &str, exchange_rates: &BTreeMap<String, f64>) -> Vec<(Amount, u64, bool)> {
+    get_amounts(content)
+        .into_iter()
+        .filter_map(|parsed| match parsed {
+            Parsed::Found(amount) if amount.value == 0 => None,
+            Parsed::Found(amount) => {
+                let number = to_euros(&amount, exchange_rates)?;
+                Some((amount, number, false))
+            }
+            // Amounts without an exchange rate keep their value, as they are too big anyway
+            Parsed::TooBig(amount) => {
+                let number = to_euros(&amount, exchange_rates).unwrap_or(amount.value);
+                Some((amount, number, true))
+            }
+        })
+        .collect()
+}
+
+/// Converts an amount of money to euros.
+///
+/// # Arguments
+///
+/// * `amount`: The amount of money found in a message
+/// * `exchange_rates`: The value of one unit of the other currencies in euros
+///
+/// returns: Option<u64>: None, if there is no exchange rate for the currency.
+fn to_euros(amount: &Amount, exchange_rates: &BTreeMap<String, f64>) -> Option<u64> {
+    match (amount.currency, exchange_rates.get(amount.currency.code())) {
+        (Currency::Euro, _) => Some(amount.value),
+        (_, Some(rate)) => Some((amount.value as f64 * rate).round() as u64),
+        (currency, None) => {
+            debug!("No exchange rate for {} configured.", currency.code());
+            None
+        }
+    }
+}
+
+/// Sums up amounts of euros. Debts are subtracted.
+///
+/// # Arguments
+///
+/// * `amounts`: The amounts found by [find_amounts]
+///
+/// returns: (u64, bool): The value of the sum and if it is debt
+fn sum(amounts: &[(Amount, u64, bool)]) -> (u64, bool) {
+    let sum: i128 = amounts
+        .iter()
+        .map(|(amount, number, _)| match amount.debt {
+            true => -i128::from(*number),
+            false => i128::from(*number),
+        })
+        .sum();
+    (
+        u64::try_from(sum.unsigned_abs()).unwrap_or(u64::MAX),
+        sum < 0,
+    )
+}
+
+/// Repeats an amount of money as it was written in the message, e.g. "5 Dollar? Das sind ja 5
+/// Euro!".
+///
+/// # Arguments
+///
+/// * `amount`: The amount of money found in a message
+/// * `number`: The amount in euros
+///
+/// returns: String
+fn original(amount: &Amount, number: u64) -> String {
+    // Negative amounts are debt
+    let debt = match amount.debt {
+        true => " Schulden",
+        false => "",
+    };
+    // Mention the original currency, if it was not in euros
+    match amount.currency {
+        Currency::Euro => format!("{} Euro{}?", number, debt),
+        currency => format!(
+            "{} {}{}? Das sind ja {} Euro!",
+            amount.value,
+            currency.name(),
+            debt,
+            number
+        ),
+    }
+}
+
+/// Converts an amount of euros to Mark, or to the currency of a year.
+///
+/// # Arguments
+///
+/// * `original`: How the amount was written in the message, see [original]
+/// * `number`: The amount in euros
+/// * `conversion`: The amount in the currency of a year, in the historical mode
+/// * `messages`: The configured answers
+///
+/// returns: String
+fn describe(
+    original: String,
+    number: u64,
+    conversion: Option<Conversion>,
+    messages: &ConversionMessages,
+) -> String {
+    match (number > THRESHOLD, conversion) {
+        // If the number is bigger than 100,000 send an "Error" message
+        (true, _) => messages.too_big.clone(),
+        // If a year was mentioned, send what the amount was worth back then
+        (false, Some(conversion)) => format!(
+            "{} Im Jahr {} waren das {} {}! Heute wären das {} Euro!",
+            original,
+            conversion.year,
+            format_money(conversion.value),
+            conversion.currency.name(),
+            format_money(conversion.today)
+        ),
+        // If the number is smaller than 100,000 send a computed message.
+        (false, None) => messages
+            .conversion
+            .replace("{amount}", &original)
+            .replace("{mark}", &(number * 2).to_string())
+            .replace("{ostmark}", &(number * 4).to_string())
+            .replace("{schwarzmarkt}", &(number * 8).to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_are_summed_up_in_euros() {
+        let exchange_rates = BTreeMap::from([("USD".to_string(), 0.5), ("CHF".to_string(), 2.0)]);
+        let cases = [
+            ("10 € und 20 € zusammen", (30, false)),
+            ("10 $ und 20 € zusammen", (25, false)),
+            ("-50 € und 20 € insgesamt", (30, true)),
+            ("50 € und -20 € insgesamt", (30, false)),
+            ("-5 € und -10 $ zusammen", (10, true)),
+            // Amounts without an exchange rate are left out
+            ("10 £ und 20 € zusammen", (20, false)),
+            // Amounts that are too big are converted, too
+            ("300.000 $ und 20 € zusammen", (150_020, false)),
+            ("-300.000 chf und 20 € zusammen", (599_980, true)),
+            (
+                "neunhunderttausend millionen milliarden euro und 1 € zusammen",
+                (u64::MAX, false),
+            ),
+            (
+                "neunhunderttausend millionen milliarden euro und -1 € zusammen",
+                (u64::MAX - 1, false),
+            ),
+        ];
+        for (content, expected) in cases {
+            let amounts = find_amounts(content, &exchange_rates);
+            assert_eq!(sum(&amounts), expected, "{}", content);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//!
+//! This module applies the configured unit converters of the Känguru Knecht to messages, e.g.
+//! "5 km" -> "Das sind ja 48 Fußballfelder!".
+//!
+//! The converters are configured in the config file, see [Converter].
+//!
+
+use crate::config::converter::Converter;
+
+/// Function to apply all converters to a message.
+///
+/// # Arguments
+///
+/// * `message`: The message to search for amounts
+/// * `converters`: The configured converters
+///
+/// returns: Vec<String>: The answers of the converters, in the order of the converters and the
+/// amounts in the message.
+///
+/// # Examples
+///
+/// ```
+/// // With the converter
+/// // { pattern = '(?P<amount>\d+(?:,\d+)?) ?km\b', factor = 9.5, template = "{amount} km? Das sind ja {result} Fußballfelder!" }
+/// assert_eq!(
+///     convert_units("Ich bin 5 km gelaufen", &converters),
+///     vec!["5 km? Das sind ja 48 Fußballfelder!"]
+/// );
+/// ```
+pub fn convert_units(message: &str, converters: &[Converter]) -> Vec<String> {
+    converters
+        .iter()
+        .flat_map(|converter| {
+            converter
+                .pattern
+                .0
+                .captures_iter(message)
+                .filter_map(move |captures| {
+                    let amount = captures.name("amount")?.as_str();
+                    let result = parse_amount(amount)? * converter.factor;
+                    Some(
+                        converter
+                            .template
+                            .replace("{amount}", amount)
+                            .replace("{result}", &format_result(result)),
+                    )
+                })
+        })
+        .collect()
+}
+
+/// Parses a number the german way, with dots as thousands separators and a decimal comma.
+fn parse_amount(amount: &str) -> Option<f64> {
+    amount
+        .replace('.', "")
+        .replace(',', ".")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Formats a result without decimal places, unless it is small.
+fn format_result(result: f64) -> String {
+    match result.abs() >= 10.0 {
+        true => format!("{:.0}", result),
+        false => format!("{:.2}", result)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .replace('.', ","),
+    }
+}
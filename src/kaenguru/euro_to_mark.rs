@@ -1,70 +1,299 @@
+use crate::kaenguru::number_words;
 use crate::regex;
+use regex::Captures;
+use std::ops::Range;
 
-/// The errors that can occur in the
-pub enum Error {
-    /// When the number that is found is bigger than the threshold. Default: 100,000
-    TooBig,
-    /// When no number could be found in the input string
-    InvalidInput,
+/// Amounts above this are too much for the DDR
+pub const THRESHOLD: u64 = 100_000;
+
+/// An amount of money that was found in a message
+pub enum Parsed {
+    /// An amount up to the [THRESHOLD]
+    Found(Amount),
+    /// An amount bigger than the [THRESHOLD]. Its value is saturated at [u64::MAX], if it does
+    /// not even fit into a number.
+    TooBig(Amount),
+}
+
+/// The currencies that are recognized in messages
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Currency {
+    /// €, EUR or Euro
+    Euro,
+    /// $, USD or Dollar
+    Dollar,
+    /// CHF or Franken
+    Franc,
+    /// £, GBP or Pfund
+    Pound,
 }
 
-/// Function to extract the last euro amount from a message
+impl Currency {
+    /// The ISO 4217 code of this currency, which is used in the table of exchange rates
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Euro => "EUR",
+            Currency::Dollar => "USD",
+            Currency::Franc => "CHF",
+            Currency::Pound => "GBP",
+        }
+    }
+
+    /// The german name of this currency
+    pub fn name(&self) -> &'static str {
+        match self {
+            Currency::Euro => "Euro",
+            Currency::Dollar => "Dollar",
+            Currency::Franc => "Franken",
+            Currency::Pound => "Pfund",
+        }
+    }
+
+    /// Gets the currency of a symbol, code or name found in a message
+    fn from_token(token: &str) -> Option<Currency> {
+        match token.to_lowercase().as_str() {
+            "€" | "eur" | "euro" | "euros" => Some(Currency::Euro),
+            "$" | "usd" | "dollar" | "dollars" => Some(Currency::Dollar),
+            "chf" | "franken" => Some(Currency::Franc),
+            "£" | "gbp" | "pfund" => Some(Currency::Pound),
+            _ => None,
+        }
+    }
+}
+
+/// An amount of money found in a message
+pub struct Amount {
+    /// The amount in the currency it was written in
+    pub value: u64,
+    /// The currency of the amount
+    pub currency: Currency,
+    /// If the amount was negative, i.e. debt
+    pub debt: bool,
+    /// Where the amount was found in the message
+    pub span: Range<usize>,
+}
+
+/// Function to extract all amounts of money from a message, in the order they were written.
+///
+/// Besides digits, the amount can be written out in german, have a magnitude (k, Tsd., Mio.,
+/// Mrd.) and be negative. Decimal places are cut off, unless the amount has a magnitude.
+///
+/// | Message                          | Amount          |
+/// |----------------------------------|-----------------|
+/// | `99,10 €`                        | 99 Euro         |
+/// | `98923 EUR`                      | 98923 Euro      |
+/// | `91.897 Dollar`                  | 91897 Dollar    |
+/// | `$5`                             | 5 Dollar        |
+/// | `CHF 20`                         | 20 Franken      |
+/// | `3 £`                            | 3 Pfund         |
+/// | `ein Euro`                       | 1 Euro          |
+/// | `zwanzig Euro`                   | 20 Euro         |
+/// | `dreihundertfünfundzwanzig €`    | 325 Euro        |
+/// | `kaffee und zwanzig euro`        | 20 Euro         |
+/// | `5k€`                            | 5000 Euro       |
+/// | `2 Tsd. EUR`                     | 2000 Euro       |
+/// | `0,05 Mio. €`                    | 50000 Euro      |
+/// | `1,5 Mio. EUR`                   | TooBig: 1500000 |
+/// | `drei Millionen €`               | TooBig: 3000000 |
+/// | `99999999999999999999999 €`      | TooBig: u64::MAX|
+/// | `-50 €`                          | 50 Euro debt    |
+/// | `10-50 €`                        | 50 Euro         |
+/// | `12 eurostücke`                  | -               |
+/// | `50 ct`                          | -               |
 ///
 /// # Arguments
 ///
-/// * `message`: The message string to extract the amount of euros from
+/// * `message`: The lowercase message string to extract the amount of money from
 ///
-/// returns: Result<u64, Error>
+/// returns: Vec<Parsed>: The amounts. It is empty, if no amount was found.
 ///
 /// # Examples
 ///
 /// ```
-/// let number = match get_euro(&_new_message.content.to_lowercase()) {
-///     Ok(n) => n,
-///     Err(why) => match why {
-///         kaenguru::TooBig => {
-///             println!("Last number in input > 100,000!");
-///             return
-///         },
-///         kaenguru::InvalidInput => {
-///             println!("no number could be found in this input")
-///             return
-///         }
+/// // Brot kostet 3 € und Butter 2 €
+/// for amount in get_amounts(&_new_message.content.to_lowercase()) {
+///     match amount {
+///         Parsed::Found(amount) => println!("{} {}", amount.value, amount.currency.name()),
+///         Parsed::TooBig(_) => println!("Number in input > 100,000!"),
 ///     }
-/// };
+/// }
 /// ```
-pub fn get_euro(message: &str) -> Result<u64, Error> {
-    // The regular expression used to parse the message into correctly formatted euro amounts
-    //
-    // # Examples
-    //
-    // * 99,10 € -> 99
-    // * 98923 € -> 98923
-    // * 91.897 € -> 91897
-    // * 99,10 EUR -> 99
-    // * 98923 EUR -> 98923
-    // * 91.897 EUR -> 91897
-    let result = regex!(r"(?is)(?:\d\.?)*\d(?:,\d+)? ?(?:EUR|€)")
-        .find_iter(message)
-        .last();
-    if result == None {
-        return Err(Error::InvalidInput);
-    }
-    let result = result.unwrap().as_str();
-    let mut number: u64 = 0;
-
-    for c in result.chars() {
-        if c == '.' {
-            continue;
-        } else if c.is_digit(10) {
-            number = number * 10 + c.to_digit(10).unwrap() as u64;
-            if number > 100000 {
-                return Err(Error::TooBig);
+pub fn get_amounts(message: &str) -> Vec<Parsed> {
+    // The regular expression used to find the amounts in the message.
+    // The currency can be written in front of or after the number, but number words only work
+    // in front of the currency.
+    regex!(
+        r"(?i)(?:(?P<prefix>\$|£|€|\b(?:usd|chf|gbp|eur)) ?(?P<prefixed>-?(?:\d\.?)*\d(?:,\d+)?)(?: ?(?P<prefixed_magnitude>(?:k|tsd|tausend|mio|millionen|million|mrd|milliarden|milliarde)\b\.?))?|(?:(?P<number>-?(?:\d\.?)*\d(?:,\d+)?)|(?P<words>\b(?:null|ein(?:s|e[nmr]?)?|zwei|zwo|drei|vier|fünf|fuenf|sechs?|sieb(?:en)?|acht|neun|zehn|elf|zwölf|zwoelf|zwanzig|dreißig|dreissig|zig|und|hundert|tausend|million(?:en)?|milliarden?)+(?: (?:null|ein(?:s|e[nmr]?)?|zwei|zwo|drei|vier|fünf|fuenf|sechs?|sieb(?:en)?|acht|neun|zehn|elf|zwölf|zwoelf|zwanzig|dreißig|dreissig|zig|und|hundert|tausend|million(?:en)?|milliarden?)+)*))(?: ?(?P<magnitude>(?:k|tsd|tausend|mio|millionen|million|mrd|milliarden|milliarde)\b\.?))? ?(?P<suffix>€|\$|£|(?:euros?|eur|usd|dollars?|chf|franken|gbp|pfund)\b))"
+    )
+    .captures_iter(message)
+    .filter_map(|captures| parse_amount(message, &captures))
+    .collect()
+}
+
+/// Checks if a message mentions a currency, e.g. "Was kostet das in Euro?".
+pub fn mentions_currency(message: &str) -> bool {
+    regex!(r"(?i)€|\$|£|\b(?:euros?|eur|usd|dollars?|chf|franken|gbp|pfund)\b").is_match(message)
+}
+
+/// Parses a single amount found by the regular expression in [get_amounts].
+///
+/// returns: Option<Parsed>: None, if the words in front of the currency are not a number.
+fn parse_amount(message: &str, captures: &Captures) -> Option<Parsed> {
+    let currency = captures.name("prefix").or(captures.name("suffix"))?;
+    let currency = Currency::from_token(currency.as_str())?;
+    let magnitude = captures
+        .name("prefixed_magnitude")
+        .or(captures.name("magnitude"))
+        .map_or(1, |magnitude| magnitude_value(magnitude.as_str()));
+    let whole = captures.get(0)?;
+
+    let amount = match captures.name("prefixed").or(captures.name("number")) {
+        Some(number) => Amount {
+            value: parse_number(number.as_str(), magnitude),
+            currency,
+            // A minus only means debt, if it is not part of a range like "10-50 €"
+            debt: number.as_str().starts_with('-')
+                && !message[..number.start()]
+                    .chars()
+                    .next_back()
+                    .is_some_and(char::is_alphanumeric),
+            span: whole.range(),
+        },
+        None => {
+            // The words can start with some that do not belong to the number, e.g.
+            // "und zwanzig euro"
+            let words = captures.name("words")?;
+            let (offset, value) = std::iter::once(0)
+                .chain(
+                    words
+                        .as_str()
+                        .match_indices(' ')
+                        .map(|(index, _)| index + 1),
+                )
+                .find_map(|offset| {
+                    Some((offset, number_words::parse(&words.as_str()[offset..])?))
+                })?;
+            Amount {
+                value: value.saturating_mul(magnitude),
+                currency,
+                debt: false,
+                span: words.start() + offset..whole.end(),
             }
-        } else {
-            break;
         }
+    };
+    Some(match amount.value > THRESHOLD {
+        true => Parsed::TooBig(amount),
+        false => Parsed::Found(amount),
+    })
+}
+
+/// Gets the value of a magnitude like "Mio." that is written after a number.
+fn magnitude_value(magnitude: &str) -> u64 {
+    match magnitude.trim_end_matches('.').to_lowercase().as_str() {
+        "k" | "tsd" | "tausend" => 1_000,
+        "mio" | "million" | "millionen" => 1_000_000,
+        "mrd" | "milliarde" | "milliarden" => 1_000_000_000,
+        _ => 1,
+    }
+}
+
+/// Parses a number with dots as thousands separators and multiplies it with its magnitude.
+/// Decimal places are cut off, unless they are needed because of the magnitude (1,5 Mio.).
+/// Numbers that do not fit into an u64 are saturated.
+fn parse_number(number: &str, magnitude: u64) -> u64 {
+    let (integer, fraction) = number.split_once(',').unwrap_or((number, ""));
+    let mut out: u64 = 0;
+
+    for c in integer.chars().filter(char::is_ascii_digit) {
+        out = out
+            .saturating_mul(10)
+            .saturating_add(c.to_digit(10).unwrap() as u64);
+    }
+
+    out = out.saturating_mul(magnitude);
+    let mut scale = magnitude;
+    for c in fraction.chars().filter(char::is_ascii_digit) {
+        scale /= 10;
+        out = out.saturating_add(c.to_digit(10).unwrap() as u64 * scale);
     }
 
-    Ok(number)
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Describes the amounts found in a message, e.g. "50 EUR debt" or "TooBig 1500000"
+    fn amounts(message: &str) -> Vec<String> {
+        get_amounts(&message.to_lowercase())
+            .iter()
+            .map(|parsed| match parsed {
+                Parsed::Found(amount) => format!(
+                    "{} {}{}",
+                    amount.value,
+                    amount.currency.code(),
+                    if amount.debt { " debt" } else { "" }
+                ),
+                Parsed::TooBig(amount) => format!("TooBig {}", amount.value),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_row_of_the_table_is_parsed() {
+        let max = format!("TooBig {}", u64::MAX);
+        let cases: [(&str, Vec<&str>); 28] = [
+            ("99,10 €", vec!["99 EUR"]),
+            ("98923 EUR", vec!["98923 EUR"]),
+            ("91.897 Dollar", vec!["91897 USD"]),
+            ("1.000.000 €", vec!["TooBig 1000000"]),
+            ("$5", vec!["5 USD"]),
+            ("CHF 20", vec!["20 CHF"]),
+            ("3 £", vec!["3 GBP"]),
+            ("£3", vec!["3 GBP"]),
+            ("5 Franken", vec!["5 CHF"]),
+            ("ein Euro", vec!["1 EUR"]),
+            ("zwanzig Euro", vec!["20 EUR"]),
+            ("dreihundertfünfundzwanzig €", vec!["325 EUR"]),
+            ("kaffee und zwanzig euro", vec!["20 EUR"]),
+            ("5k€", vec!["5000 EUR"]),
+            ("2 Tsd. EUR", vec!["2000 EUR"]),
+            ("0,05 Mio. €", vec!["50000 EUR"]),
+            ("1,5 Mio. EUR", vec!["TooBig 1500000"]),
+            ("drei Millionen €", vec!["TooBig 3000000"]),
+            ("99999999999999999999999 €", vec![&max]),
+            ("hunderttausend millionen milliarden €", vec![&max]),
+            ("neunhunderttausend millionen milliarden euro", vec![&max]),
+            ("zwanzig milliarden mrd. €", vec![&max]),
+            ("-50 €", vec!["50 EUR debt"]),
+            ("10-50 €", vec!["50 EUR"]),
+            ("12 eurostücke", vec![]),
+            ("50 ct", vec![]),
+            ("Brot kostet 3 € und Butter 2 €", vec!["3 EUR", "2 EUR"]),
+            ("Das kostet nichts", vec![]),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(amounts(message), expected, "message {:?}", message);
+        }
+    }
+
+    #[test]
+    fn number_words_start_at_the_number() {
+        let message = "kaffee und zwanzig euro";
+        match get_amounts(message).as_slice() {
+            [Parsed::Found(amount)] => assert_eq!(&message[amount.span.clone()], "zwanzig euro"),
+            _ => panic!("Expected a single amount in {:?}", message),
+        }
+    }
+
+    #[test]
+    fn currencies_are_mentioned() {
+        for message in ["was kostet das in euro?", "in $", "chf", "wie viele pfund?"] {
+            assert!(mentions_currency(message), "message {:?}", message);
+        }
+        for message in ["eurostücke", "hallo", "5 ct"] {
+            assert!(!mentions_currency(message), "message {:?}", message);
+        }
+    }
 }
@@ -0,0 +1,273 @@
+//!
+//! This module converts euros to the currencies that were used in Germany in a given year and
+//! estimates what the amount would be worth today.
+//!
+//! Everything is computed from tables that are bundled with the program, so no network access
+//! is needed. The values are rough approximations and only meant for jokes:
+//!
+//! * D-Mark (1948 - 2001): The official rate of 1.95583 D-Mark per euro
+//! * Reichsmark (1924 - 1947): 10 Reichsmark per D-Mark, as in the currency reform of 1948
+//! * Rentenmark (1923): 1 Rentenmark per Reichsmark
+//!
+
+use crate::regex;
+use chrono::{Datelike, Local};
+use std::ops::Range;
+
+/// The official rate at which the D-Mark was replaced by the euro
+const DM_PER_EURO: f64 = 1.95583;
+
+/// The rate at which the Reichsmark was replaced by the D-Mark in 1948
+const RM_PER_DM: f64 = 10.0;
+
+/// The approximate value of one unit of the currency of a year in today's euros.
+/// The years in between are interpolated.
+const PURCHASING_POWER: [(i32, f64); 23] = [
+    (1923, 4.4),
+    (1925, 4.4),
+    (1930, 4.2),
+    (1933, 5.0),
+    (1938, 4.8),
+    (1944, 4.2),
+    (1947, 3.4),
+    (1948, 3.39),
+    (1950, 3.49),
+    (1955, 3.13),
+    (1960, 2.84),
+    (1965, 2.49),
+    (1970, 2.18),
+    (1975, 1.6),
+    (1980, 1.3),
+    (1985, 1.11),
+    (1990, 1.02),
+    (1995, 0.81),
+    (2001, 0.75),
+    (2002, 1.46),
+    (2010, 1.28),
+    (2015, 1.24),
+    (2024, 1.0),
+];
+
+/// The currencies that were used in Germany since 1923
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum HistoricalCurrency {
+    /// From November 1923 until the Reichsmark was introduced in 1924
+    Rentenmark,
+    /// From 1924 until the currency reform of 1948
+    Reichsmark,
+    /// From 1948 until the euro cash was introduced in 2002
+    DMark,
+    /// Since 2002
+    Euro,
+}
+
+impl HistoricalCurrency {
+    /// Gets the currency that was used in Germany in a year.
+    ///
+    /// returns: Option<HistoricalCurrency>: None, if the year is before the Rentenmark or in the
+    /// future.
+    pub fn of_year(year: i32) -> Option<HistoricalCurrency> {
+        match year {
+            1923 => Some(HistoricalCurrency::Rentenmark),
+            1924..=1947 => Some(HistoricalCurrency::Reichsmark),
+            1948..=2001 => Some(HistoricalCurrency::DMark),
+            year if year >= 2002 && year <= Local::now().year() => Some(HistoricalCurrency::Euro),
+            _ => None,
+        }
+    }
+
+    /// The german name of this currency
+    pub fn name(&self) -> &'static str {
+        match self {
+            HistoricalCurrency::Rentenmark => "Rentenmark",
+            HistoricalCurrency::Reichsmark => "Reichsmark",
+            HistoricalCurrency::DMark => "D-Mark",
+            HistoricalCurrency::Euro => "Euro",
+        }
+    }
+
+    /// The number of units of this currency that are worth one euro
+    fn per_euro(&self) -> f64 {
+        match self {
+            HistoricalCurrency::Rentenmark | HistoricalCurrency::Reichsmark => {
+                DM_PER_EURO * RM_PER_DM
+            }
+            HistoricalCurrency::DMark => DM_PER_EURO,
+            HistoricalCurrency::Euro => 1.0,
+        }
+    }
+}
+
+/// An amount of euros converted to the currency of a year
+pub struct Conversion {
+    /// The year to which the amount was converted
+    pub year: i32,
+    /// The currency that was used in that year
+    pub currency: HistoricalCurrency,
+    /// The amount in that currency
+    pub value: f64,
+    /// What the amount would be worth today, in euros
+    pub today: f64,
+}
+
+/// Function to find the last year mentioned in a message, e.g. "1990 hat das 5 € gekostet".
+///
+/// # Arguments
+///
+/// * `message`: The message to search
+/// * `amount`: Where the amount of money is in the message, which is not counted as a year
+///
+/// returns: Option<i32>: None, if no year was found in which there was a known currency.
+pub fn find_year(message: &str, amount: &Range<usize>) -> Option<i32> {
+    regex!(r"\b(?:19|20)\d\d\b")
+        .find_iter(message)
+        .filter(|year| year.end() <= amount.start || year.start() >= amount.end)
+        .filter_map(|year| year.as_str().parse().ok())
+        .filter(|year| HistoricalCurrency::of_year(*year).is_some())
+        .last()
+}
+
+/// Function to convert euros to the currency of a year.
+///
+/// # Arguments
+///
+/// * `euros`: The amount of euros
+/// * `year`: The year to convert to
+///
+/// returns: Option<Conversion>: None, if there was no known currency in the year.
+///
+/// # Examples
+///
+/// ```
+/// let conversion = convert(5, 1990).unwrap();
+/// // 5 Euro im Jahr 1990? Das waren damals 9,78 D-Mark!
+/// println!(
+///     "{} Euro im Jahr {}? Das waren damals {} {}!",
+///     5,
+///     conversion.year,
+///     format_money(conversion.value),
+///     conversion.currency.name()
+/// );
+/// ```
+pub fn convert(euros: u64, year: i32) -> Option<Conversion> {
+    let currency = HistoricalCurrency::of_year(year)?;
+    let value = euros as f64 * currency.per_euro();
+    Some(Conversion {
+        year,
+        currency,
+        value,
+        today: value * purchasing_power(year),
+    })
+}
+
+/// Formats an amount of money the german way, with two decimal places and a comma.
+pub fn format_money(value: f64) -> String {
+    format!("{:.2}", value).replace('.', ",")
+}
+
+/// Looks up the value of one unit of the currency of a year in today's euros.
+fn purchasing_power(year: i32) -> f64 {
+    match PURCHASING_POWER
+        .windows(2)
+        .find(|pair| pair[0].0 <= year && year <= pair[1].0)
+    {
+        Some([(start, from), (end, to)]) => {
+            from + (to - from) * f64::from(year - start) / f64::from(end - start)
+        }
+        // Everything after the table is already worth about today's value
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_year_has_its_currency() {
+        let cases = [
+            (1922, None),
+            (1923, Some(HistoricalCurrency::Rentenmark)),
+            (1924, Some(HistoricalCurrency::Reichsmark)),
+            (1947, Some(HistoricalCurrency::Reichsmark)),
+            (1948, Some(HistoricalCurrency::DMark)),
+            (2001, Some(HistoricalCurrency::DMark)),
+            (2002, Some(HistoricalCurrency::Euro)),
+            (Local::now().year(), Some(HistoricalCurrency::Euro)),
+            (Local::now().year() + 1, None),
+        ];
+        for (year, expected) in cases {
+            assert_eq!(HistoricalCurrency::of_year(year), expected, "year {}", year);
+        }
+    }
+
+    #[test]
+    fn the_last_year_outside_of_the_amount_is_found() {
+        let cases = [
+            ("1990 hat das 5 € gekostet", "5 €", Some(1990)),
+            ("1970 oder 1990 hat das 5 € gekostet", "5 €", Some(1990)),
+            ("das hat 5 € gekostet", "5 €", None),
+            // Years without a known currency are skipped
+            ("1990 oder 1900 hat das 5 € gekostet", "5 €", Some(1990)),
+            ("1850 hat das 5 € gekostet", "5 €", None),
+            // The amount itself is not a year
+            ("das hat 1990 € gekostet", "1990 €", None),
+            ("12345 € sind kein jahr", "12345 €", None),
+        ];
+        for (message, amount, expected) in cases {
+            let start = message.find(amount).unwrap();
+            let amount = start..start + amount.len();
+            assert_eq!(
+                find_year(message, &amount),
+                expected,
+                "message {:?}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn euros_are_converted_to_the_currency_of_the_year() {
+        let conversion = convert(5, 1990).unwrap();
+        assert_eq!(conversion.currency, HistoricalCurrency::DMark);
+        assert_eq!(format_money(conversion.value), "9,78");
+        assert_eq!(format_money(conversion.today), "9,97");
+
+        let conversion = convert(1, 1930).unwrap();
+        assert_eq!(conversion.currency, HistoricalCurrency::Reichsmark);
+        assert_eq!(format_money(conversion.value), "19,56");
+
+        let conversion = convert(5, 2024).unwrap();
+        assert_eq!(conversion.currency, HistoricalCurrency::Euro);
+        assert_eq!(format_money(conversion.value), "5,00");
+        assert_eq!(format_money(conversion.today), "5,00");
+
+        assert!(convert(5, 1900).is_none());
+    }
+
+    #[test]
+    fn the_purchasing_power_is_interpolated() {
+        let cases = [
+            (1923, 4.4),
+            (1990, 1.02),
+            (1992, 0.936),
+            (2024, 1.0),
+            (2030, 1.0),
+        ];
+        for (year, expected) in cases {
+            assert!(
+                (purchasing_power(year) - expected).abs() < 1e-9,
+                "year {}: {}",
+                year,
+                purchasing_power(year)
+            );
+        }
+    }
+
+    #[test]
+    fn money_is_formatted_the_german_way() {
+        assert_eq!(format_money(9.7791), "9,78");
+        assert_eq!(format_money(0.0), "0,00");
+        assert_eq!(format_money(1234.5), "1234,50");
+    }
+}
@@ -0,0 +1,171 @@
+//!
+//! This module parses numbers that are written out in german, e.g. "dreihundertfünfundzwanzig"
+//! or "zwei Millionen".
+//!
+//! Numbers up to the billions (Milliarden) are understood. Umlauts can also be written as "ue"
+//! and "oe", and "ß" as "ss".
+//!
+
+/// The words that multiply the number in front of them, with their plural ending and value.
+/// They are ordered from the biggest to the smallest.
+const MAGNITUDES: [(&str, &str, u64); 4] = [
+    ("milliarde", "n", 1_000_000_000),
+    ("million", "en", 1_000_000),
+    ("tausend", "", 1_000),
+    ("hundert", "", 100),
+];
+
+/// Function to parse a german number word. Spaces between the parts are ignored.
+/// Numbers that do not fit into an u64 are saturated at [u64::MAX].
+///
+/// # Arguments
+///
+/// * `words`: The lowercase number word
+///
+/// returns: Option<u64>: None, if `words` is not a number
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(parse("zwanzig"), Some(20));
+/// assert_eq!(parse("ein"), Some(1));
+/// assert_eq!(parse("dreihundertfünfundzwanzig"), Some(325));
+/// assert_eq!(parse("drei millionen"), Some(3_000_000));
+/// assert_eq!(parse("zweitausendeinhundertundzehn"), Some(2_110));
+/// assert_eq!(parse("kaffee"), None);
+/// ```
+pub fn parse(words: &str) -> Option<u64> {
+    let words: String = words.split_whitespace().collect();
+    parse_from(&words, 0)
+}
+
+/// Parses a number word, which is smaller than the magnitude at the index in [MAGNITUDES].
+fn parse_from(words: &str, magnitude: usize) -> Option<u64> {
+    for (index, (name, plural, factor)) in MAGNITUDES.iter().enumerate().skip(magnitude) {
+        if let Some((left, right)) = words.split_once(name) {
+            // "hundert" means "einhundert"
+            let left = match left {
+                "" => 1,
+                left => parse_from(left, index + 1)?,
+            };
+            let right = right.strip_prefix(plural).unwrap_or(right);
+            let right = right.strip_prefix("und").unwrap_or(right);
+            let right = match right {
+                "" => 0,
+                right => parse_from(right, index + 1)?,
+            };
+            // Numbers that do not fit into an u64 are saturated, so they are too big for sure
+            return Some(
+                left.checked_mul(*factor)
+                    .and_then(|value| value.checked_add(right))
+                    .unwrap_or(u64::MAX),
+            );
+        }
+    }
+    below_hundred(words)
+}
+
+/// Parses a number word smaller than 100, e.g. "fünfundzwanzig".
+fn below_hundred(words: &str) -> Option<u64> {
+    match words.split_once("und") {
+        Some((unit, ten)) => match (single_word(unit)?, single_word(ten)?) {
+            (unit @ 1..=9, ten) if ten >= 20 && ten % 10 == 0 => Some(ten + unit),
+            _ => None,
+        },
+        None => single_word(words),
+    }
+}
+
+/// Looks up a number, that is written as a single word.
+fn single_word(word: &str) -> Option<u64> {
+    Some(match word {
+        "null" => 0,
+        "ein" | "eins" | "eine" | "einen" | "einem" | "einer" => 1,
+        "zwei" | "zwo" => 2,
+        "drei" => 3,
+        "vier" => 4,
+        "fünf" | "fuenf" => 5,
+        "sechs" => 6,
+        "sieben" => 7,
+        "acht" => 8,
+        "neun" => 9,
+        "zehn" => 10,
+        "elf" => 11,
+        "zwölf" | "zwoelf" => 12,
+        "dreizehn" => 13,
+        "vierzehn" => 14,
+        "fünfzehn" | "fuenfzehn" => 15,
+        "sechzehn" => 16,
+        "siebzehn" => 17,
+        "achtzehn" => 18,
+        "neunzehn" => 19,
+        "zwanzig" => 20,
+        "dreißig" | "dreissig" => 30,
+        "vierzig" => 40,
+        "fünfzig" | "fuenfzig" => 50,
+        "sechzig" => 60,
+        "siebzig" => 70,
+        "achtzig" => 80,
+        "neunzig" => 90,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_words_are_parsed() {
+        let cases = [
+            ("null", Some(0)),
+            ("ein", Some(1)),
+            ("eins", Some(1)),
+            ("einen", Some(1)),
+            ("zwo", Some(2)),
+            ("zwölf", Some(12)),
+            ("zwoelf", Some(12)),
+            ("siebzehn", Some(17)),
+            ("zwanzig", Some(20)),
+            ("dreißig", Some(30)),
+            ("dreissig", Some(30)),
+            ("fünfundzwanzig", Some(25)),
+            ("fuenfundzwanzig", Some(25)),
+            ("einundneunzig", Some(91)),
+            ("hundert", Some(100)),
+            ("einhundert", Some(100)),
+            ("hundertundzehn", Some(110)),
+            ("dreihundertfünfundzwanzig", Some(325)),
+            ("tausend", Some(1_000)),
+            ("zweitausendeinhundertundzehn", Some(2_110)),
+            (
+                "neunundneunzigtausendneunhundertneunundneunzig",
+                Some(99_999),
+            ),
+            ("eine million", Some(1_000_000)),
+            ("drei millionen", Some(3_000_000)),
+            ("zwei milliarden", Some(2_000_000_000)),
+            ("drei millionen zweihunderttausend", Some(3_200_000)),
+            ("neunhunderttausend millionen milliarden", Some(u64::MAX)),
+            ("hunderttausend millionen milliarden", Some(u64::MAX)),
+        ];
+        for (words, expected) in cases {
+            assert_eq!(parse(words), expected, "words {:?}", words);
+        }
+    }
+
+    #[test]
+    fn other_words_are_not_numbers() {
+        for words in [
+            "",
+            "kaffee",
+            "und",
+            "zwanzigundfünf",
+            "zehnundzwanzig",
+            "hunderthundert",
+            "tausend kaffee",
+        ] {
+            assert_eq!(parse(words), None, "words {:?}", words);
+        }
+    }
+}
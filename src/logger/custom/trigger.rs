@@ -30,7 +30,6 @@ impl CustomTrigger {
     ///
     /// * See [logger::default_logger](crate::logger::default_logger), line 139 for a detailed
     ///   example.
-
     pub fn new(limit: u64) -> CustomTrigger {
         CustomTrigger { limit }
     }
@@ -11,6 +11,8 @@
 //!     * All logs above or at level of `WARN`, will **always** be logged to stderr.
 //!     * All logs below `WARN` will be logged to stdout. This can be configured on
 //!       release builds with the `LOGGING_LEVEL_THRESHOLD` environment variable.
+//!       When a command like `migrate` is run, they are logged to stderr as well, so stdout
+//!       only contains the output of the command.
 //!
 //! The logger's default pattern is:
 //! ```log
@@ -48,38 +50,36 @@ pub mod custom;
 /// # Arguments
 ///
 /// * `level`: The minimum level to log with: DEBUG/TRACE for debug builds and INFO for releases.
+/// * `info_target`: Where logs below `WARN` are logged to on the console.
 ///
 /// returns: Handle
 ///
 /// # Examples
 ///
 /// ```
-/// default_logger(LevelFilter::Debug);
+/// default_logger(LevelFilter::Debug, Target::Stdout);
 /// ```
-fn default_logger(level: log::LevelFilter) {
+fn default_logger(level: log::LevelFilter, info_target: Target) {
     let mut warnings: Vec<String> = Vec::new();
 
     // Give the user to specify their own logging file
-    match env::var("LOGGING_CONFIG_FILE") {
-        Ok(s) => {
-            if Path::new(&s).exists() {
-                match log4rs::init_file(&s, Deserializers::default()) {
-                    Ok(_) => {
-                        warn!("Using custom logger configuration at: {}", s);
-                        trace!("Config contents:\n{}", fs::read_to_string(&s).unwrap());
-                        return;
-                    }
-                    Err(why) => {
-                        warnings.push(format!(
-                            "\"{}\" is not a valid config file. Using defaults!",
-                            s
-                        ));
-                        warnings.push(format!("Error message: {}", why));
-                    }
+    if let Ok(s) = env::var("LOGGING_CONFIG_FILE") {
+        if Path::new(&s).exists() {
+            match log4rs::init_file(&s, Deserializers::default()) {
+                Ok(_) => {
+                    warn!("Using custom logger configuration at: {}", s);
+                    trace!("Config contents:\n{}", fs::read_to_string(&s).unwrap());
+                    return;
+                }
+                Err(why) => {
+                    warnings.push(format!(
+                        "\"{}\" is not a valid config file. Using defaults!",
+                        s
+                    ));
+                    warnings.push(format!("Error message: {}", why));
                 }
             }
         }
-        _ => {}
     }
 
     // Get changeable logger attributes from environment
@@ -150,7 +150,7 @@ fn default_logger(level: log::LevelFilter) {
 
     // STDOUT and STDERR with the specified pattern
     let stdout = ConsoleAppender::builder()
-        .target(Target::Stdout)
+        .target(info_target)
         .encoder(Box::new(PatternEncoder::new(&pattern)))
         .build();
     let stderr = ConsoleAppender::builder()
@@ -237,29 +237,39 @@ fn default_logger(level: log::LevelFilter) {
 /// A wrapper function for [logger_init::default_logger](crate::logger_init::default_logger).
 /// For debug builds the log level is set down to DEBUG.
 ///
+/// # Arguments
+///
+/// * `command`: If a command is run, whose output is printed to stdout. All logs are written
+///   to stderr then.
+///
 /// returns: Handle
 ///
 /// # Examples
 ///
 /// ```
-/// logger_init::init();
+/// logger_init::init(false);
 /// ```
-pub fn init() {
-    default_logger(log::LevelFilter::Debug)
+pub fn init(command: bool) {
+    default_logger(log::LevelFilter::Debug, info_target(command))
 }
 
 #[cfg(not(debug_assertions))]
 /// A wrapper function for [logger_init::default_logger](crate::logger_init::default_logger).
 /// For release builds the log level is set down to INFO, if not set via the environment.
 ///
+/// # Arguments
+///
+/// * `command`: If a command is run, whose output is printed to stdout. All logs are written
+///   to stderr then.
+///
 /// returns: Handle
 ///
 /// # Examples
 ///
 /// ```
-/// logger_init::init();
+/// logger_init::init(false);
 /// ```
-pub fn init() {
+pub fn init(command: bool) {
     use log::LevelFilter::*;
 
     let logging_level = match env::var("LOGGING_LEVEL_THRESHOLD") {
@@ -273,7 +283,7 @@ pub fn init() {
         },
         Err(_) => Info,
     };
-    let handle = default_logger(logging_level);
+    let handle = default_logger(logging_level, info_target(command));
 
     if logging_level != Info {
         log::warn!(
@@ -284,3 +294,11 @@ pub fn init() {
 
     handle
 }
+
+/// Where logs below `WARN` are logged to on the console: Only commands log everything to stderr.
+fn info_target(command: bool) -> Target {
+    match command {
+        true => Target::Stderr,
+        false => Target::Stdout,
+    }
+}
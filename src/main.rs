@@ -2,31 +2,42 @@ mod common;
 mod config;
 mod kaenguru;
 mod logger;
+mod markov;
+mod matcher;
+mod normalize;
+mod privacy;
+mod quotes;
 mod replies;
+mod scheduler;
+mod storage;
 mod xd;
 
 use log::{debug, error, info, trace, warn};
 use serenity::prelude::*;
 use std::{
-    borrow::Borrow, env, process::exit, sync::atomic::Ordering, thread::sleep, time::Duration,
+    borrow::Borrow, env, fs, process::exit, sync::atomic::Ordering, thread::sleep, time::Duration,
 };
 use tokio::{runtime::Runtime, time::Instant};
 
-use crate::config::CONFIG;
+use crate::config::{secrets::Secret, Bots, CONFIG};
 
 /// Function to start a new instance of the autokommentator bot
 async fn start_xd() {
     // Get the token from the configuration
     let xd_token = match env::var("DISCORD_TOKEN_XD") {
-        Ok(s) => s,
+        Ok(s) => Secret::from(s),
         Err(_) => {
             let token = match CONFIG.lock() {
-                Ok(config) => match &config.autokommentator.token {
-                    Some(s) => String::from(s),
-                    None => {
+                Ok(config) => match config.autokommentator.token() {
+                    Ok(Some(s)) => s,
+                    Ok(None) => {
                         warn!("No token configured for the autokommentator");
                         return;
                     }
+                    Err(why) => {
+                        error!("{}", why);
+                        return;
+                    }
                 },
                 Err(why) => {
                     panic!(
@@ -40,11 +51,12 @@ async fn start_xd() {
             token
         }
     };
-    let mut xd_client = Client::builder(xd_token, GatewayIntents::default())
+    let mut xd_client = Client::builder(xd_token.expose(), GatewayIntents::default())
         .event_handler(xd::XDHandler)
         .await
         .expect("Error creating client");
 
+    scheduler::start(Bots::Autokommentator, xd_client.http.clone());
     if let Err(why) = xd_client.start().await {
         error!("An error occurred while running the client: {:?}", why)
     }
@@ -54,15 +66,19 @@ async fn start_xd() {
 async fn start_kg() {
     // Get the token from the configuration
     let kg_token = match env::var("DISCORD_TOKEN_KAENGURU") {
-        Ok(s) => s,
+        Ok(s) => Secret::from(s),
         Err(_) => {
             let token = match CONFIG.lock() {
-                Ok(config) => match &config.kaenguru.token {
-                    Some(s) => String::from(s),
-                    None => {
+                Ok(config) => match config.kaenguru.token() {
+                    Ok(Some(s)) => s,
+                    Ok(None) => {
                         warn!("No token configured for the Kaenguru");
                         return;
                     }
+                    Err(why) => {
+                        error!("{}", why);
+                        return;
+                    }
                 },
                 Err(why) => {
                     panic!(
@@ -76,19 +92,82 @@ async fn start_kg() {
             token
         }
     };
-    let mut kg_client = Client::builder(kg_token, GatewayIntents::default())
+    let mut kg_client = Client::builder(kg_token.expose(), GatewayIntents::default())
         .event_handler(kaenguru::KaenguruHandler)
         .await
         .expect("Error creating client");
 
+    scheduler::start(Bots::KaenguruKnecht, kg_client.http.clone());
     if let Err(why) = kg_client.start().await {
         error!("An error occurred while running the client: {:?}", why)
     }
 }
 
+/// Runs a command passed on the command line instead of starting the bots.
+///
+/// Available commands:
+///
+/// * `migrate [--dry-run]`: Upgrades the config file to the current version.
+///   With `--dry-run`, the changes are only printed as a diff, in which all tokens are redacted.
+/// * `schema [--fragment] [FILE]`: Prints the JSON Schema of the config file or writes it to
+///   `FILE`. With `--fragment`, the schema is for included files, in which no section is required.
+///
+/// # Arguments
+///
+/// * `args`: The command line arguments without the program name
+///
+/// returns: i32: The exit code of the command
+fn run_command(args: &[String]) -> i32 {
+    match args[0].as_str() {
+        "migrate" => {
+            let dry_run = args.iter().any(|arg| arg == "--dry-run");
+            match config::migrate::migrate_file(&config::config_file_location(), dry_run) {
+                Ok(Some(diff)) => {
+                    print!("{}", diff);
+                    0
+                }
+                Ok(None) => 0,
+                Err(why) => {
+                    error!("Could not migrate the config file: {}", why);
+                    1
+                }
+            }
+        }
+        "schema" => {
+            let fragment = args.iter().any(|arg| arg == "--fragment");
+            let schema = config::json_schema(fragment);
+            match args.iter().skip(1).find(|arg| !arg.starts_with("--")) {
+                Some(file) => match fs::write(file, schema) {
+                    Ok(_) => {
+                        info!("Written JSON Schema of the config file to {}", file);
+                        0
+                    }
+                    Err(why) => {
+                        error!("Could not write the JSON Schema to {}: {}", file, why);
+                        1
+                    }
+                },
+                None => {
+                    println!("{}", schema);
+                    0
+                }
+            }
+        }
+        command => {
+            error!("Unknown command: {}", command);
+            error!("Available commands: migrate [--dry-run], schema [--fragment] [FILE]");
+            1
+        }
+    }
+}
+
 /// Main entry point to this program
 fn main() {
-    logger::init();
+    let args: Vec<String> = env::args().skip(1).collect();
+    logger::init(!args.is_empty());
+    if !args.is_empty() {
+        exit(run_command(&args));
+    }
 
     // Clears the old log file, so that the first log entry is the log afterwards
     if logger::custom::trigger::LOG_FILE_EXISTS.load(Ordering::Relaxed) {
@@ -107,6 +186,7 @@ fn main() {
             }
         }
     );
+
     info!("Starting \"Känguru Rechenkencht\" and \"XD-Bot\"...");
     // Use tokio to run multiple bots at the same time
     let start = Instant::now();
@@ -149,6 +229,7 @@ fn main() {
             "Thanks for using these bots! If you like them, consider staring this repo on GitHub:"
         );
         info!("    https://github.com/MaFeLP/discord_bots");
+        markov::save();
         exit(0);
     })
     .expect("Error setting Ctrl-C handler");
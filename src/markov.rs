@@ -0,0 +1,290 @@
+//!
+//! This module generates replies with a word-level Markov chain, which is learned from the
+//! messages the Autokommentator sees.
+//!
+//! Only the guilds in `autokommentator.markov.guilds` of the config file are learned from, and
+//! messages of users who have opted out or are on the blocklist are ignored. Links and mentions
+//! are never learned.
+//!
+//! Every guild has its own chain, which is kept apart for every user, so that the words of a user
+//! can be forgotten again, when they opt out or delete their data with `/meinedaten`. The chains
+//! are stored in the file in the environment `MARKOV_FILE`, or if it was not set, `markov.json`.
+//! The file is written after every [SAVE_EVERY] learned messages, after words were forgotten and
+//! when the program is stopped.
+//!
+
+use crate::config::{Generator, Reply, CONFIG};
+use crate::privacy;
+use crate::storage;
+use log::{debug, error, info};
+use once_cell::sync::Lazy;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use serenity::model::{
+    channel::Message,
+    id::{GuildId, UserId},
+};
+use std::{collections::BTreeMap, env, fs, path::Path, sync::Mutex};
+
+/// The number of learned messages after which the chains are written to the file
+const SAVE_EVERY: u32 = 50;
+
+/// The maximum number of words that are learned from a single message
+const MAX_WORDS_PER_MESSAGE: usize = 100;
+
+/// The word that marks the start and the end of a message
+const BOUNDARY: &str = "";
+
+/// The chains of all guilds
+static MODELS: Lazy<Mutex<Models>> = Lazy::new(|| Mutex::new(Models::load()));
+
+/// A Markov chain: How often a word was followed by another word
+type Chain = BTreeMap<String, BTreeMap<String, u32>>;
+
+#[derive(Serialize, Deserialize, Default)]
+/// The Markov chains of all guilds
+struct Models {
+    /// The chain of every guild, learned from every user separately
+    #[serde(default)]
+    guilds: BTreeMap<u64, BTreeMap<u64, Chain>>,
+    /// The number of messages that were learned since the chains were written to the file
+    #[serde(skip)]
+    unsaved: u32,
+}
+
+impl Models {
+    /// Loads the chains from the file, or starts with empty chains if it does not exist yet or
+    /// cannot be read.
+    fn load() -> Models {
+        let markov_file = markov_file_location();
+        if !Path::new(&markov_file).exists() {
+            debug!(
+                "Markov file {} does not exist yet. Starting empty.",
+                markov_file
+            );
+            return Models::default();
+        }
+        // The chains are not needed to run the bots, so they rather start over than not at all
+        match fs::read_to_string(&markov_file).map(|content| serde_json::from_str(&content)) {
+            Ok(Ok(models)) => models,
+            Ok(Err(why)) => {
+                error!(
+                    "The markov file {} is invalid: {}. Starting empty.",
+                    markov_file, why
+                );
+                Models::default()
+            }
+            Err(why) => {
+                error!(
+                    "Could not read the markov file {}: {}. Starting empty.",
+                    markov_file, why
+                );
+                Models::default()
+            }
+        }
+    }
+
+    /// Writes the chains to the file (see [storage::write_file]).
+    fn save(&mut self) {
+        let markov_file = markov_file_location();
+        let result = serde_json::to_string(self)
+            .map_err(|why| why.to_string())
+            .and_then(|content| {
+                storage::write_file(&markov_file, &content).map_err(|why| why.to_string())
+            });
+        match result {
+            Ok(_) => self.unsaved = 0,
+            Err(why) => error!("Could not write the markov file {}: {}", markov_file, why),
+        }
+    }
+}
+
+/// Learns the words of a message, if it was sent in a guild that is configured to be learned
+/// from.
+///
+/// # Arguments
+///
+/// * `message`: The message to learn from
+///
+/// returns: ()
+pub fn learn(message: &Message) {
+    let guild = match message.guild_id {
+        Some(guild) => guild,
+        None => return,
+    };
+    let enabled = match CONFIG.lock() {
+        Ok(config) => config.autokommentator.markov.guilds.contains(&guild.get()),
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
+        }
+    };
+    if !enabled || privacy::is_ignored(message.author.id, Some(guild)) {
+        return;
+    }
+
+    let words: Vec<&str> = message
+        .content
+        .split_whitespace()
+        // Links and mentions should never be repeated
+        .filter(|word| !word.contains("://") && !word.starts_with("<") && !word.starts_with('@'))
+        .take(MAX_WORDS_PER_MESSAGE)
+        .collect();
+    if words.is_empty() {
+        return;
+    }
+
+    let mut models = MODELS.lock().unwrap();
+    let chain = models
+        .guilds
+        .entry(guild.get())
+        .or_default()
+        .entry(message.author.id.get())
+        .or_default();
+    let mut previous = BOUNDARY;
+    for word in words.iter().copied().chain([BOUNDARY]) {
+        *chain
+            .entry(previous.to_string())
+            .or_default()
+            .entry(word.to_string())
+            .or_default() += 1;
+        previous = word;
+    }
+    models.unsaved += 1;
+    if models.unsaved >= SAVE_EVERY {
+        models.save();
+    }
+}
+
+/// Generates a message from the chain of a guild.
+///
+/// # Arguments
+///
+/// * `guild`: The guild whose chain is used
+/// * `max_words`: The maximum number of words of the message
+///
+/// returns: Option<String>: None, if nothing was learned in the guild yet.
+pub fn generate(guild: GuildId, max_words: usize) -> Option<String> {
+    let models = MODELS.lock().unwrap();
+    let chains = models.guilds.get(&guild.get())?;
+    let mut rng = rand::thread_rng();
+
+    let mut words: Vec<&str> = Vec::new();
+    let mut current = BOUNDARY;
+    while words.len() < max_words {
+        // The chains of all users of the guild are used together
+        let mut followers: BTreeMap<&str, u32> = BTreeMap::new();
+        for chain in chains.values() {
+            for (word, count) in chain.get(current).into_iter().flatten() {
+                *followers.entry(word).or_default() += count;
+            }
+        }
+        let followers: Vec<(&str, u32)> = followers.into_iter().collect();
+        current = followers
+            .choose_weighted(&mut rng, |(_, count)| *count)
+            .ok()?
+            .0;
+        if current == BOUNDARY {
+            break;
+        }
+        words.push(current);
+    }
+    match words.is_empty() {
+        true => None,
+        false => Some(words.join(" ")),
+    }
+}
+
+/// Generates the message of a [Reply::Generated]. Other replies are returned unchanged.
+///
+/// # Arguments
+///
+/// * `reply`: The reply that was chosen from a response pool
+/// * `guild`: The guild in which the reply is sent, if any
+///
+/// returns: Option<Reply>: None, if no message could be generated.
+pub fn resolve(reply: Reply, guild: Option<GuildId>) -> Option<Reply> {
+    match reply {
+        Reply::Generated {
+            generator: Generator::Markov,
+            max_words,
+        } => {
+            let message = generate(guild?, max_words);
+            if message.is_none() {
+                debug!("Nothing was learned in guild {:?} yet", guild);
+            }
+            message.map(Reply::Message)
+        }
+        reply => Some(reply),
+    }
+}
+
+/// Writes the chains to the file, if something was learned since they were written last.
+pub fn save() {
+    let mut models = MODELS.lock().unwrap();
+    if models.unsaved > 0 {
+        models.save();
+        info!("Saved the markov chains");
+    }
+}
+
+/// Counts the words that were learned from a user in every guild.
+///
+/// # Arguments
+///
+/// * `user`: The user to collect the data of
+///
+/// returns: serde_json::Value
+pub fn about(user: UserId) -> serde_json::Value {
+    let models = MODELS.lock().unwrap();
+    let learned: BTreeMap<String, u32> = models
+        .guilds
+        .iter()
+        .filter_map(|(guild, chains)| {
+            let words = chains
+                .get(&user.get())?
+                .values()
+                .flat_map(|followers| followers.iter())
+                .filter(|(word, _)| word.as_str() != BOUNDARY)
+                .map(|(_, count)| count)
+                .sum();
+            Some((guild.to_string(), words))
+        })
+        .collect();
+    serde_json::json!({ "learned_words": learned })
+}
+
+/// Forgets all words that were learned from a user and writes the chains to the file.
+///
+/// # Arguments
+///
+/// * `user`: The user whose words are forgotten
+///
+/// returns: ()
+pub fn forget(user: UserId) {
+    let mut models = MODELS.lock().unwrap();
+    let mut forgotten = false;
+    for chains in models.guilds.values_mut() {
+        forgotten |= chains.remove(&user.get()).is_some();
+    }
+    models.guilds.retain(|_, chains| !chains.is_empty());
+    if forgotten {
+        models.save();
+        info!("Forgot the words learned from user {}", user);
+    }
+}
+
+/// Gets the location of the markov file.
+///
+/// returns: String
+fn markov_file_location() -> String {
+    match env::var("MARKOV_FILE") {
+        Ok(o) => o,
+        Err(_) => {
+            debug!("MARKOV_FILE is not set. Using default \"markov.json\"...");
+            "markov.json".to_string()
+        }
+    }
+}
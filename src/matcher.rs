@@ -0,0 +1,432 @@
+//!
+//! This module searches messages for the triggers of many responses at once.
+//!
+//! All triggers of a bot are compiled into [Aho-Corasick](aho_corasick) automatons when the
+//! configuration is loaded: one automaton for every [Normalizer] used by the responses. A message
+//! then only has to be normalized and scanned once per normalizer, no matter how many triggers
+//! are configured.
+//!
+//! Items can also opt into fuzzy matching. Their triggers are then also found, if every word of
+//! the trigger differs by at most a few edits from the words in the message
+//! (e.g. "schnappspraline" instead of "schnapspraline").
+//!
+//! This module only depends on [normalize](crate::normalize), so it can also be used by the
+//! benchmarks in `benches/`.
+//!
+
+use crate::normalize::Normalizer;
+use aho_corasick::AhoCorasick;
+
+/// Words with less characters than this always have to match exactly, even in fuzzy mode.
+/// Otherwise short words like "xd" would be found in nearly every message.
+const FUZZY_MIN_WORD_LENGTH: usize = 4;
+
+/// An item that can be searched for with a [Matcher]
+pub trait Searchable {
+    /// All triggers of this item
+    fn triggers(&self) -> Vec<String>;
+
+    /// How the triggers of this item and the messages are normalized before they are compared
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::default()
+    }
+
+    /// The maximum edit distance per word, if the triggers of this item should also be found
+    /// when they contain typos. `None` disables fuzzy matching.
+    fn max_distance(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// An item that was found in a message
+pub struct Match<'a, T> {
+    /// The item that was found
+    pub item: &'a T,
+    /// The trigger of the item as it was configured
+    pub trigger: &'a str,
+    /// The sum of the edit distances of all words of the trigger. `0` for exact matches.
+    pub distance: usize,
+}
+
+/// The index of an item and one of its triggers as it was configured
+type Pattern = (usize, String);
+
+/// A trigger that is also found with typos
+struct FuzzyPattern {
+    /// The index of the pattern in [Group::patterns]
+    pattern: usize,
+    /// The normalized words of the trigger
+    words: Vec<String>,
+    /// The maximum edit distance per word
+    max_distance: usize,
+}
+
+/// The triggers of all items that use the same normalizer
+struct Group {
+    /// How the triggers and messages are normalized for this group
+    normalizer: Normalizer,
+    /// The automaton that contains the normalized triggers of this group
+    automaton: AhoCorasick,
+    /// Maps the index of a pattern in the automaton to its item and trigger
+    patterns: Vec<Pattern>,
+    /// The triggers of all items of this group with fuzzy matching enabled
+    fuzzy: Vec<FuzzyPattern>,
+}
+
+/// A [Group] while its triggers are collected
+struct GroupBuilder {
+    normalizer: Normalizer,
+    /// The normalized triggers, from which the automaton is built
+    normalized: Vec<String>,
+    patterns: Vec<Pattern>,
+    fuzzy: Vec<FuzzyPattern>,
+}
+
+/// An index over the triggers of a list of items (usually [responses](crate::config::Response)).
+pub struct Matcher<T> {
+    /// The triggers, grouped by their normalizer
+    groups: Vec<Group>,
+    /// The items that are searched for
+    items: Vec<T>,
+}
+
+impl<T: Searchable> Matcher<T> {
+    /// Compiles the triggers of all items into a new matcher.
+    ///
+    /// # Arguments
+    ///
+    /// * `items`: The items that should be found in a message. Their order is kept.
+    ///
+    /// returns: Matcher<T>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let matcher = Matcher::new(vec![vec!["xd".to_string()]]);
+    /// assert!(matcher.find_where("XD", |_| true).is_some());
+    /// ```
+    pub fn new(items: Vec<T>) -> Matcher<T> {
+        let mut groups: Vec<GroupBuilder> = Vec::new();
+        for (idx, item) in items.iter().enumerate() {
+            let normalizer = item.normalizer();
+            let group = match groups.iter().position(|g| g.normalizer == normalizer) {
+                Some(position) => &mut groups[position],
+                None => {
+                    groups.push(GroupBuilder {
+                        normalizer,
+                        normalized: Vec::new(),
+                        patterns: Vec::new(),
+                        fuzzy: Vec::new(),
+                    });
+                    groups.last_mut().unwrap()
+                }
+            };
+            for trigger in item.triggers() {
+                let normalized = group.normalizer.normalize(&trigger);
+                // An empty trigger would be found in every message
+                if normalized.is_empty() {
+                    continue;
+                }
+                if let Some(max_distance) = item.max_distance() {
+                    group.fuzzy.push(FuzzyPattern {
+                        pattern: group.patterns.len(),
+                        words: words(&normalized).map(String::from).collect(),
+                        max_distance,
+                    });
+                }
+                group.normalized.push(normalized);
+                group.patterns.push((idx, trigger));
+            }
+        }
+
+        let groups = groups
+            .into_iter()
+            .map(|group| Group {
+                normalizer: group.normalizer,
+                automaton: AhoCorasick::new(group.normalized)
+                    .expect("Could not build the trigger index"),
+                patterns: group.patterns,
+                fuzzy: group.fuzzy,
+            })
+            .collect();
+
+        Matcher { groups, items }
+    }
+}
+
+impl<T> Matcher<T> {
+    /// Calls `found` with the index of the item, the trigger and the edit distance of every match
+    /// in the message.
+    fn search<'a, F>(&'a self, message: &str, mut found: F)
+    where
+        F: FnMut(usize, &'a str, usize),
+    {
+        for group in &self.groups {
+            let message = group.normalizer.normalize(message);
+            for m in group.automaton.find_overlapping_iter(&message) {
+                let (idx, trigger) = &group.patterns[m.pattern().as_usize()];
+                found(*idx, trigger, 0);
+            }
+
+            if group.fuzzy.is_empty() {
+                continue;
+            }
+            let message_words: Vec<&str> = words(&message).collect();
+            for fuzzy in &group.fuzzy {
+                if let Some(distance) = fuzzy_distance(&message_words, fuzzy) {
+                    let (idx, trigger) = &group.patterns[fuzzy.pattern];
+                    found(*idx, trigger, distance);
+                }
+            }
+        }
+    }
+
+    /// Finds the first item (in the order they were passed to [Matcher::new]) that has a
+    /// trigger in the message and fulfills a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `message`: The message to search for triggers
+    /// * `condition`: Items for which this returns `false` are ignored
+    ///
+    /// returns: Option<Match<T>>: The item, the trigger and the edit distance that was found
+    pub fn find_where<F>(&self, message: &str, condition: F) -> Option<Match<'_, T>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut first: Option<(usize, &str, usize)> = None;
+        self.search(message, |idx, trigger, distance| {
+            if first.is_none_or(|(first, _, first_distance)| {
+                idx < first || (idx == first && distance < first_distance)
+            }) && condition(&self.items[idx])
+            {
+                first = Some((idx, trigger, distance));
+            }
+        });
+        first.map(|(idx, trigger, distance)| Match {
+            item: &self.items[idx],
+            trigger,
+            distance,
+        })
+    }
+
+    /// Finds all items that have a trigger in the message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message`: The message to search for triggers
+    ///
+    /// returns: Vec<Match<T>>: Every item together with the best of its triggers that was found
+    /// (the one with the lowest edit distance and then the longest one), in the order they were
+    /// passed to [Matcher::new].
+    pub fn find_all(&self, message: &str) -> Vec<Match<'_, T>> {
+        let mut found: Vec<Option<(&str, usize)>> = vec![None; self.items.len()];
+        self.search(message, |idx, trigger, distance| {
+            if found[idx].is_none_or(|(best, best_distance)| {
+                distance < best_distance
+                    || (distance == best_distance && best.len() < trigger.len())
+            }) {
+                found[idx] = Some((trigger, distance));
+            }
+        });
+        found
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, found)| {
+                found.map(|(trigger, distance)| Match {
+                    item: &self.items[idx],
+                    trigger,
+                    distance,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<T: Searchable> Default for Matcher<T> {
+    fn default() -> Self {
+        Matcher::new(Vec::new())
+    }
+}
+
+impl Searchable for Vec<String> {
+    fn triggers(&self) -> Vec<String> {
+        self.clone()
+    }
+}
+
+/// Splits a text into its words and drops all punctuation
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+/// Searches the words of a message for consecutive words that are similar to all words of the
+/// trigger.
+///
+/// returns: Option<usize>: The lowest sum of the edit distances of all words, if the trigger was
+/// found.
+fn fuzzy_distance(message: &[&str], fuzzy: &FuzzyPattern) -> Option<usize> {
+    if fuzzy.words.is_empty() || message.len() < fuzzy.words.len() {
+        return None;
+    }
+    message
+        .windows(fuzzy.words.len())
+        .filter_map(|window| {
+            window
+                .iter()
+                .zip(&fuzzy.words)
+                .map(|(word, trigger)| {
+                    let distance = strsim::osa_distance(word, trigger);
+                    let short = trigger.chars().count() < FUZZY_MIN_WORD_LENGTH;
+                    match (short, distance) {
+                        (true, 0) => Some(0),
+                        (true, _) => None,
+                        (false, d) if d <= fuzzy.max_distance => Some(d),
+                        (false, _) => None,
+                    }
+                })
+                .sum::<Option<usize>>()
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalize::Step;
+
+    /// A response with its own normalization and fuzzy matching
+    struct Item {
+        triggers: Vec<&'static str>,
+        steps: Vec<Step>,
+        max_distance: Option<usize>,
+    }
+
+    impl Searchable for Item {
+        fn triggers(&self) -> Vec<String> {
+            self.triggers
+                .iter()
+                .map(|trigger| trigger.to_string())
+                .collect()
+        }
+
+        fn normalizer(&self) -> Normalizer {
+            Normalizer::new(&self.steps)
+        }
+
+        fn max_distance(&self) -> Option<usize> {
+            self.max_distance
+        }
+    }
+
+    /// Creates a matcher with a single item
+    fn matcher(triggers: Vec<&'static str>, max_distance: Option<usize>) -> Matcher<Item> {
+        Matcher::new(vec![Item {
+            triggers,
+            steps: Vec::new(),
+            max_distance,
+        }])
+    }
+
+    #[test]
+    fn triggers_with_typos_are_found() {
+        let cases = [
+            // Trigger, message, maximum distance, expected distance
+            (
+                "schnapspraline",
+                "Ich will eine Schnapspraline",
+                Some(1),
+                Some(0),
+            ),
+            (
+                "schnapspraline",
+                "Ich will eine Schnappspraline",
+                Some(1),
+                Some(1),
+            ),
+            (
+                "schnapspraline",
+                "Ich will eine Schnpaspraline",
+                Some(1),
+                Some(1),
+            ),
+            (
+                "schnapspraline",
+                "Ich will eine Schnappsprahline",
+                Some(1),
+                None,
+            ),
+            (
+                "schnapspraline",
+                "Ich will eine Schnappsprahline",
+                Some(2),
+                Some(2),
+            ),
+            (
+                "schnapspraline",
+                "Ich will eine Schnappspraline",
+                None,
+                None,
+            ),
+            ("guten morgen", "Gutn morgn!", Some(1), Some(2)),
+            ("guten morgen", "Gutn", Some(1), None),
+            ("guten morgen", "morgen guten", Some(1), None),
+            // Short words always have to match exactly
+            ("xd", "xf", Some(1), None),
+            ("xd", "Das ist XD", Some(1), Some(0)),
+            ("gute nacht xd", "gute nacht xf", Some(1), None),
+            ("gute nacht xd", "gutee nacht xd", Some(1), Some(1)),
+        ];
+        for (trigger, message, max_distance, expected) in cases {
+            let matcher = matcher(vec![trigger], max_distance);
+            let found = matcher.find_where(message, |_| true);
+            assert_eq!(
+                found.map(|found| found.distance),
+                expected,
+                "trigger {:?} in {:?}",
+                trigger,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn exact_matches_are_preferred() {
+        let matcher = matcher(vec!["hallo", "halo"], Some(1));
+        let found = matcher.find_all("Hallo!");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].trigger, "hallo");
+        assert_eq!(found[0].distance, 0);
+    }
+
+    #[test]
+    fn the_first_item_with_a_trigger_is_found() {
+        let matcher = Matcher::new(vec![
+            Item {
+                triggers: vec!["kaffee"],
+                steps: Vec::new(),
+                max_distance: Some(1),
+            },
+            Item {
+                triggers: vec!["xd"],
+                steps: vec![Step::Repeated],
+                max_distance: None,
+            },
+        ]);
+        let found = matcher.find_where("xddd und kafee", |_| true).unwrap();
+        assert_eq!(found.trigger, "kaffee");
+        let found = matcher
+            .find_where("xddd und kafee", |item| item.max_distance.is_none())
+            .unwrap();
+        assert_eq!(found.trigger, "xd");
+        assert_eq!(matcher.find_all("xddd und kafee").len(), 2);
+        assert!(matcher.find_where("nichts", |_| true).is_none());
+    }
+
+    #[test]
+    fn empty_triggers_are_skipped() {
+        let matcher = matcher(vec![""], Some(1));
+        assert!(matcher.find_where("irgendwas", |_| true).is_none());
+    }
+}
@@ -0,0 +1,178 @@
+//!
+//! This module normalizes messages and triggers before they are compared, so that triggers are
+//! also found when they are written a bit differently, e.g. "Schnapspraliné", a full-width "ｘｄ"
+//! or "xddddd".
+//!
+//! Which [steps](Step) are applied can be configured per response. Text is always lowercased.
+//!
+//! This module does not depend on the rest of the program, so it can also be used by the
+//! benchmarks in `benches/`.
+//!
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Characters that are not visible and are often used to hide words from filters
+const ZERO_WIDTH: [char; 6] = [
+    '\u{00AD}', // Soft hyphen
+    '\u{200B}', // Zero width space
+    '\u{200C}', // Zero width non-joiner
+    '\u{200D}', // Zero width joiner
+    '\u{2060}', // Word joiner
+    '\u{FEFF}', // Zero width no-break space
+];
+
+#[derive(Deserialize, JsonSchema, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[serde(rename_all = "kebab-case")]
+/// A single step of the normalization of a text
+pub enum Step {
+    /// Removes invisible characters like zero width spaces and joiners
+    ZeroWidth,
+    /// Applies the unicode normalization form NFKC, e.g. "ｘｄ" becomes "xd"
+    Nfkc,
+    /// Replaces "ß" with "ss"
+    SharpS,
+    /// Removes diacritics from letters, e.g. "é" becomes "e" and "ä" becomes "a"
+    Diacritics,
+    /// Removes all whitespace, e.g. "X D" becomes "xd". Beware, that this also
+    /// matches triggers across words ("max dauer" contains "xd")!
+    Spaces,
+    /// Collapses repeated characters into one, e.g. "xddddd" becomes "xd"
+    Repeated,
+}
+
+/// A pipeline of normalization [steps](Step).
+///
+/// The steps are always applied in the order they are declared in [Step], no matter in which
+/// order they were configured.
+#[derive(Clone, Default, Eq, PartialEq, Hash, Debug)]
+pub struct Normalizer {
+    steps: Vec<Step>,
+}
+
+impl Normalizer {
+    /// Creates a new normalizer that applies the given steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `steps`: The steps to apply. Duplicates are ignored.
+    ///
+    /// returns: Normalizer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let normalizer = Normalizer::new(&[Step::Nfkc, Step::Repeated]);
+    /// assert_eq!(normalizer.normalize("ＸＤＤＤ"), "xd");
+    /// ```
+    pub fn new(steps: &[Step]) -> Normalizer {
+        let mut steps = steps.to_vec();
+        steps.sort();
+        steps.dedup();
+        Normalizer { steps }
+    }
+
+    /// Lowercases a text and applies all steps of this normalizer to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`: The text to normalize
+    ///
+    /// returns: String
+    pub fn normalize(&self, input: &str) -> String {
+        let mut text = input.to_string();
+        let mut lowercased = false;
+
+        for step in &self.steps {
+            // Lowercase after NFKC, so that full-width letters are lowercased as well
+            if !lowercased && *step > Step::Nfkc {
+                text = text.to_lowercase();
+                lowercased = true;
+            }
+            text = match step {
+                Step::ZeroWidth => text.chars().filter(|c| !ZERO_WIDTH.contains(c)).collect(),
+                Step::Nfkc => text.nfkc().collect(),
+                // The text is already lowercase, so "ẞ" has become "ß"
+                Step::SharpS => text.replace('ß', "ss"),
+                Step::Diacritics => text
+                    .nfd()
+                    .filter(|c| !is_combining_mark(*c))
+                    .nfc()
+                    .collect(),
+                Step::Spaces => text.chars().filter(|c| !c.is_whitespace()).collect(),
+                Step::Repeated => {
+                    let mut out = String::with_capacity(text.len());
+                    let mut last = None;
+                    for c in text.chars() {
+                        if last != Some(c) {
+                            out.push(c);
+                        }
+                        last = Some(c);
+                    }
+                    out
+                }
+            };
+        }
+
+        if lowercased {
+            text
+        } else {
+            text.to_lowercase()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_step_normalizes_the_text() {
+        let cases: [(&[Step], &str, &str); 14] = [
+            (&[], "XD", "xd"),
+            (&[], "Schnapspraliné", "schnapspraliné"),
+            (&[Step::ZeroWidth], "x\u{200B}d\u{00AD}", "xd"),
+            (&[Step::Nfkc], "ＸＤ", "xd"),
+            (&[Step::Nfkc], "ﬁ", "fi"),
+            (&[Step::SharpS], "Große Straße", "grosse strasse"),
+            (&[Step::SharpS], "GROẞ", "gross"),
+            (&[Step::Diacritics], "Schnapspraliné", "schnapspraline"),
+            (&[Step::Diacritics], "Känguru", "kanguru"),
+            (&[Step::Spaces], "X D", "xd"),
+            (&[Step::Spaces], "x\td\n", "xd"),
+            (&[Step::Repeated], "xddddd", "xd"),
+            (&[Step::Repeated], "XDdd", "xd"),
+            // The steps run in their declared order, so "ss" is collapsed as well
+            (
+                &[
+                    Step::Repeated,
+                    Step::Spaces,
+                    Step::Diacritics,
+                    Step::SharpS,
+                    Step::Nfkc,
+                    Step::ZeroWidth,
+                ],
+                "Ｘ\u{200B} Ｄ ＤＤ ẞé",
+                "xdse",
+            ),
+        ];
+        for (steps, input, expected) in cases {
+            assert_eq!(
+                Normalizer::new(steps).normalize(input),
+                expected,
+                "steps {:?}",
+                steps
+            );
+        }
+    }
+
+    #[test]
+    fn steps_are_sorted_and_deduplicated() {
+        assert_eq!(
+            Normalizer::new(&[Step::Repeated, Step::Nfkc, Step::Repeated]),
+            Normalizer::new(&[Step::Nfkc, Step::Repeated])
+        );
+        assert_eq!(Normalizer::new(&[]), Normalizer::default());
+    }
+}
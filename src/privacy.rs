@@ -0,0 +1,146 @@
+//!
+//! This module lets users decide if the bots should reply to them, and which data is stored
+//! about them.
+//!
+//! * `/optout`: The bots do not reply to the user anymore and forget the words learned from them
+//! * `/optin`: The bots reply to the user again
+//! * `/meinedaten`: Shows or deletes everything that is stored about the user
+//!
+//! Additionally, the users in the `blocklist` of a guild in the config file are never replied to.
+//!
+
+use crate::config::CONFIG;
+use crate::markov;
+use crate::replies;
+use crate::storage::{self, DATA};
+use log::{error, info};
+use serenity::{
+    builder::{
+        CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    model::{
+        application::{CommandInteraction, CommandOptionType},
+        id::{GuildId, UserId},
+    },
+    prelude::*,
+};
+
+/// Checks if the bots should not reply to a user, because they have opted out or are on the
+/// blocklist of the guild.
+///
+/// # Arguments
+///
+/// * `user`: The author of the message
+/// * `guild`: The guild in which the message was sent, if it was not a direct message
+///
+/// returns: bool
+pub fn is_ignored(user: UserId, guild: Option<GuildId>) -> bool {
+    if DATA.lock().unwrap().opted_out.contains(&user) {
+        return true;
+    }
+    let guild = match guild {
+        Some(guild) => guild,
+        None => return false,
+    };
+    match CONFIG.lock() {
+        Ok(config) => config.blocklist.iter().any(|blocklist| {
+            blocklist.guild == guild.get() && blocklist.users.contains(&user.get())
+        }),
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
+        }
+    }
+}
+
+/// The slash commands of this module, which have to be registered with discord
+pub fn commands() -> Vec<CreateCommand> {
+    vec![
+        CreateCommand::new("optout").description("Die Bots antworten nicht mehr auf dich"),
+        CreateCommand::new("optin").description("Die Bots antworten wieder auf dich"),
+        CreateCommand::new("meinedaten")
+            .description("Zeigt oder löscht alles, was über dich gespeichert ist")
+            .add_option(
+                CreateCommandOption::new(CommandOptionType::String, "aktion", "Was passieren soll")
+                    .add_string_choice("Anzeigen", "anzeigen")
+                    .add_string_choice("Löschen", "loeschen")
+                    .required(true),
+            ),
+    ]
+}
+
+/// Runs a slash command of this module.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `command`: The slash command a user has used.
+///
+/// returns: bool: If the command belongs to this module.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> bool {
+    let user = command.user.id;
+    let answer = match command.data.name.as_str() {
+        "optout" => match storage::update(|data| data.opted_out.insert(user)) {
+            Ok(_) => {
+                markov::forget(user);
+                info!("User {} has opted out", user);
+                "Alles klar, ich antworte dir nicht mehr. Mit /optin kannst du es dir anders überlegen.".to_string()
+            }
+            Err(_) => "Das hat leider nicht geklappt. Versuch es später nochmal.".to_string(),
+        },
+        "optin" => match storage::update(|data| data.opted_out.remove(&user)) {
+            Ok(_) => {
+                info!("User {} has opted in", user);
+                "Willkommen zurück!".to_string()
+            }
+            Err(_) => "Das hat leider nicht geklappt. Versuch es später nochmal.".to_string(),
+        },
+        "meinedaten" => {
+            let action = command
+                .data
+                .options
+                .first()
+                .and_then(|option| option.value.as_str());
+            match action {
+                Some("loeschen") => match storage::update(|data| data.forget(user)) {
+                    Ok(_) => {
+                        replies::forget(user);
+                        markov::forget(user);
+                        info!("Deleted all data about user {}", user);
+                        "Alles, was über dich gespeichert war, ist gelöscht.".to_string()
+                    }
+                    Err(_) => {
+                        "Das hat leider nicht geklappt. Versuch es später nochmal.".to_string()
+                    }
+                },
+                _ => {
+                    let mut data = DATA.lock().unwrap().about(user);
+                    data["replies"] = replies::about(user);
+                    data["markov"] = markov::about(user);
+                    format!(
+                        "Das ist über dich gespeichert:\n```json\n{}\n```",
+                        serde_json::to_string_pretty(&data).unwrap()
+                    )
+                }
+            }
+        }
+        _ => return false,
+    };
+
+    // Only the user who used the command can see the answer
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(answer)
+            .ephemeral(true),
+    );
+    if let Err(why) = command.create_response(&ctx.http, response).await {
+        error!(
+            "Could not answer the command {}: {:?}",
+            command.data.name, why
+        );
+    }
+    true
+}
@@ -0,0 +1,297 @@
+//!
+//! This module contains the quotes of the Känguru Knecht.
+//!
+//! * `/zitat [stichwort]`: Posts a random quote, which contains the keyword or has it as a tag
+//! * The "Zitat des Tages" is posted every day in a configured channel
+//!
+//! The quotes are bundled from `assets/quotes.toml`, or read from the file configured in
+//! `quotes.file` when the config is loaded. A quote is not posted again within a week, unless
+//! there is no other quote left.
+//!
+
+use crate::config::{format::Format, CONFIG};
+use crate::replies::local_time;
+use crate::storage::{self, DATA};
+use chrono::{NaiveDate, Timelike, Utc};
+use log::{debug, error, info};
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serenity::{
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    },
+    http::Http,
+    model::{
+        application::{CommandInteraction, CommandOptionType},
+        id::ChannelId,
+    },
+    prelude::*,
+};
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// The quotes that are used, if no quote file is configured
+const DEFAULT_QUOTES: &str = include_str!("../assets/quotes.toml");
+
+/// The seconds after which a quote may be posted again
+const REPEAT_AFTER: i64 = 7 * 24 * 60 * 60;
+
+/// If the task that posts the "Zitat des Tages" is already running
+static DAILY_STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+/// A single quote
+pub struct Quote {
+    /// What was said
+    pub text: String,
+    /// Where it was said, e.g. the book
+    pub source: Option<String>,
+    /// Keywords by which the quote can be found
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+/// The structure of a quote file
+struct QuoteFile {
+    quotes: Vec<Quote>,
+}
+
+/// Loads the quotes from a quote file, or the bundled quotes, if no file is configured.
+///
+/// # Arguments
+///
+/// * `file`: The quote file configured in `quotes.file`
+///
+/// returns: Result<Vec<Quote>, String>: The error contains the file that could not be loaded.
+pub fn load(file: &Option<String>) -> Result<Vec<Quote>, String> {
+    let (content, format) = match file {
+        Some(file) => match fs::read_to_string(file) {
+            Ok(content) => (content, Format::from_path(file)),
+            Err(why) => return Err(format!("Could not read the quote file {}: {}", file, why)),
+        },
+        None => (DEFAULT_QUOTES.to_string(), Format::Toml),
+    };
+    let quotes = format
+        .parse(&content)
+        .and_then(|value| value.try_into::<QuoteFile>().map_err(|why| why.to_string()))
+        .map_err(|why| {
+            format!(
+                "The quote file {} is invalid: {}",
+                file.as_deref().unwrap_or("assets/quotes.toml"),
+                why
+            )
+        })?;
+    info!("Loaded {} quotes", quotes.quotes.len());
+    Ok(quotes.quotes)
+}
+
+/// Searches the quotes for a keyword in their text, source or tags.
+///
+/// # Arguments
+///
+/// * `keyword`: The keyword to search for, or None for all quotes
+///
+/// returns: Vec<Quote>
+fn search(keyword: Option<&str>) -> Vec<Quote> {
+    let keyword = keyword.map(str::to_lowercase);
+    let quotes = match CONFIG.lock() {
+        Ok(config) => config.kaenguru.quotes.quotes.clone(),
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
+        }
+    };
+    quotes
+        .into_iter()
+        .filter(|quote| match keyword {
+            None => true,
+            Some(ref keyword) => {
+                quote.text.to_lowercase().contains(keyword)
+                    || quote
+                        .source
+                        .as_ref()
+                        .is_some_and(|source| source.to_lowercase().contains(keyword))
+                    || quote.tags.iter().any(|tag| tag.to_lowercase() == *keyword)
+            }
+        })
+        .collect()
+}
+
+/// Chooses a random quote, that was not posted within the last week, and remembers that it was
+/// posted. If all quotes were posted recently, the one posted the longest time ago is chosen.
+///
+/// # Arguments
+///
+/// * `quotes`: The quotes to choose from
+///
+/// returns: Option<Quote>: None, if there are no quotes
+fn pick(quotes: Vec<Quote>) -> Option<Quote> {
+    let now = Utc::now().timestamp();
+    let posted = DATA.lock().unwrap().posted_quotes.clone();
+    let last_posted = |quote: &Quote| posted.get(&quote.text).copied();
+
+    let fresh: Vec<&Quote> = quotes
+        .iter()
+        .filter(|quote| last_posted(quote).is_none_or(|time| now - time >= REPEAT_AFTER))
+        .collect();
+    let quote = match fresh.choose(&mut rand::thread_rng()) {
+        Some(quote) => (*quote).clone(),
+        None => quotes
+            .iter()
+            .min_by_key(|quote| last_posted(quote))?
+            .clone(),
+    };
+
+    // The error is already logged, and the quote can be posted nevertheless
+    let _ = storage::update(|data| {
+        data.posted_quotes
+            .retain(|_, time| now - *time < REPEAT_AFTER);
+        data.posted_quotes.insert(quote.text.clone(), now);
+    });
+    Some(quote)
+}
+
+/// Creates the embed that shows a quote.
+fn embed(quote: &Quote) -> CreateEmbed {
+    let embed = CreateEmbed::new().description(&quote.text);
+    match quote.source {
+        Some(ref source) => embed.footer(CreateEmbedFooter::new(source)),
+        None => embed,
+    }
+}
+
+/// The slash commands of this module, which have to be registered with discord
+pub fn commands() -> Vec<CreateCommand> {
+    vec![CreateCommand::new("zitat")
+        .description("Zeigt ein zufälliges Zitat")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "stichwort",
+                "Wonach im Zitat gesucht wird",
+            )
+            .required(false),
+        )]
+}
+
+/// Runs a slash command of this module.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `command`: The slash command a user has used.
+///
+/// returns: bool: If the command belongs to this module.
+pub async fn run(ctx: &Context, command: &CommandInteraction) -> bool {
+    if command.data.name != "zitat" {
+        return false;
+    }
+    let keyword = command
+        .data
+        .options
+        .first()
+        .and_then(|option| option.value.as_str());
+
+    let message = match (pick(search(keyword)), keyword) {
+        (Some(quote), _) => CreateInteractionResponseMessage::new().embed(embed(&quote)),
+        (None, Some(keyword)) => CreateInteractionResponseMessage::new()
+            .content(format!("Zu \"{}\" kenne ich leider kein Zitat.", keyword))
+            .ephemeral(true),
+        (None, None) => CreateInteractionResponseMessage::new()
+            .content("Ich kenne leider noch keine Zitate.")
+            .ephemeral(true),
+    };
+    if let Err(why) = command
+        .create_response(&ctx.http, CreateInteractionResponse::Message(message))
+        .await
+    {
+        error!(
+            "Could not answer the command {}: {:?}",
+            command.data.name, why
+        );
+    }
+    true
+}
+
+/// Starts posting the "Zitat des Tages" in the configured channel, if it has not been started
+/// yet.
+///
+/// The quote is posted once a day, as soon as the configured hour is reached. If the bot was not
+/// running at that time, the quote is posted when it is started on the same day.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+///
+/// returns: ()
+pub fn start_daily(ctx: &Context) {
+    let (channel, hour, timezone) = match CONFIG.lock() {
+        Ok(config) => (
+            config.kaenguru.quotes.daily_channel,
+            config.kaenguru.quotes.daily_hour,
+            config.kaenguru.timezone,
+        ),
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
+        }
+    };
+    let channel = match channel {
+        Some(channel) => ChannelId::new(channel),
+        None => return,
+    };
+    // The bot is ready again after every reconnect, but only one task should post the quotes
+    if DAILY_STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    debug!("Posting the Zitat des Tages at {}:00 in {}", hour, channel);
+    let http = ctx.http.clone();
+    tokio::spawn(async move {
+        loop {
+            let now = local_time(timezone);
+            let today = now.date();
+            let last = DATA.lock().unwrap().last_daily_quote;
+            if now.hour() >= hour && last != Some(today) {
+                post_daily(&http, channel, today).await;
+            }
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }
+    });
+}
+
+/// Posts the "Zitat des Tages".
+async fn post_daily(http: &Arc<Http>, channel: ChannelId, today: NaiveDate) {
+    // Remember the day first, so a failing channel is not retried every minute
+    if storage::update(|data| data.last_daily_quote = Some(today)).is_err() {
+        return;
+    }
+    let quote = match pick(search(None)) {
+        Some(quote) => quote,
+        None => return,
+    };
+    match channel
+        .send_message(
+            http,
+            CreateMessage::new().embed(embed(&quote).title("Zitat des Tages")),
+        )
+        .await
+    {
+        Ok(_) => info!("Posted the Zitat des Tages in {}", channel),
+        Err(why) => error!("Could not post the Zitat des Tages: {:?}", why),
+    }
+}
@@ -1,22 +1,103 @@
-use crate::config::{Bots, Response, CONFIG};
+pub mod cleanup;
+pub mod history;
+
+use crate::config::{Bots, HumanDelay, MatchPolicy, Reply, Response, SequenceStep, CONFIG};
+use crate::markov;
+use crate::matcher::{Match, Matcher};
+use crate::privacy;
+use crate::replies::history::{SentReplies, HISTORY};
+use chrono::{Local, NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use log::{debug, error, info, trace};
+use once_cell::sync::Lazy;
 use rand::Rng;
 use serenity::{
-    model::channel::{Channel, Message},
+    builder::EditMessage,
+    model::{
+        channel::{Channel, Message},
+        event::MessageUpdateEvent,
+        id::{ChannelId, MessageId, UserId},
+    },
     prelude::*,
     Error,
 };
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The messages that a bot has seen in each channel
+static ACTIVITY: Lazy<Mutex<HashMap<(Bots, ChannelId), ChannelActivity>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Counts the messages in a channel, to find out if a bot should reply in it
+#[derive(Default)]
+struct ChannelActivity {
+    /// The number of messages that have been sent in the channel
+    messages: u64,
+    /// The value of `messages`, when the bot last replied in the channel
+    last_reply: Option<u64>,
+}
+
+/// The settings of a bot that are needed to reply to a message.
+/// They are copied out of the [CONFIG], so that it is not locked while replying.
+struct Settings {
+    matcher: Arc<Matcher<Response>>,
+    match_policy: MatchPolicy,
+    min_messages_between_replies: u32,
+    timezone: Option<Tz>,
+    reply_to_edits: bool,
+    delete_orphaned_replies: bool,
+    cancel_sequences_after: u32,
+    human_delay: Option<HumanDelay>,
+    delete_reaction: bool,
+}
+
+/// A response that was selected to reply to a message
+struct Selected {
+    /// The trigger that was found in the message
+    trigger: String,
+    /// The reply that was selected from the response pool
+    reply: Reply,
+    /// The time after which the reply should be deleted again
+    delete_after: Option<Duration>,
+}
 
 /// The Errors that might be thrown by [reply_to]
 pub enum ReplyError {
     /// If no reply was found in this message.
     /// Can be ignored in most cases.
     NoReplyFound,
+    /// If the reply could not be sent to discord.
     Sending(Error),
+    /// If the channel of the message could not be retrieved.
     ChannelError(Error),
+    /// If an edited message could not be retrieved.
+    Fetching(Error),
+    /// If a reply could not be edited.
+    Editing(Error),
+    /// If a reply could not be deleted.
+    Deleting(Error),
+}
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplyError::NoReplyFound => write!(f, "No reply found in the message"),
+            ReplyError::Sending(why) => write!(f, "Could not send the reply: {}", why),
+            ReplyError::ChannelError(why) => write!(f, "Could not get the channel: {}", why),
+            ReplyError::Fetching(why) => write!(f, "Could not get the message: {}", why),
+            ReplyError::Editing(why) => write!(f, "Could not edit the reply: {}", why),
+            ReplyError::Deleting(why) => write!(f, "Could not delete the reply: {}", why),
+        }
+    }
 }
 
 /// A function that searches _new\_message_ for replies configured in config.toml.
+/// Replies are skipped by chance or if the bot has replied in the channel too recently.
+/// If configured, the bot waits before every message of the reply and shows the typing indicator.
 /// For this it will first acquire the Mutex Lock for the configuration.
 ///
 /// # Arguments
@@ -53,100 +134,575 @@ pub async fn reply_to(
     new_message: &Message,
     bot: Bots,
 ) -> Result<String, ReplyError> {
-    trace!("Getting replies from configuration...");
-    let replies: Vec<Response> = {
-        let reply_vector: Vec<Response> = match CONFIG.lock() {
-            Ok(config) => {
-                // Copy replies vector
-                match bot {
-                    Bots::Autokommentator => config.autokommentator.responses.to_vec(),
-                    Bots::KaenguruKnecht => config.kaenguru.responses.to_vec(),
-                }
+    count_message(bot, new_message.channel_id);
+    reply(ctx, new_message, bot, settings(&bot)).await
+}
+
+/// Searches a message for replies and sends them, like [reply_to]. The message is not counted
+/// as a new message in its channel, so that edited messages do not count twice.
+async fn reply(
+    ctx: &Context,
+    new_message: &Message,
+    bot: Bots,
+    settings: Settings,
+) -> Result<String, ReplyError> {
+    let cooled_down = cooled_down(
+        bot,
+        new_message.channel_id,
+        settings.min_messages_between_replies,
+    );
+    if privacy::is_ignored(new_message.author.id, new_message.guild_id) {
+        debug!(
+            "User {} does not want to be replied to",
+            new_message.author.id
+        );
+        return Err(ReplyError::NoReplyFound);
+    }
+    if !cooled_down {
+        debug!(
+            "Not enough messages since the last reply in channel {}",
+            new_message.channel_id
+        );
+        return Err(ReplyError::NoReplyFound);
+    }
+
+    let selected = select_responses(&settings, new_message, true);
+    if selected.is_empty() {
+        match bot {
+            Bots::Autokommentator => {
+                debug!(target: "xdbot::xd", "No trigger found in message.")
+            }
+            Bots::KaenguruKnecht => {
+                debug!(target: "xdbot::kaenguru", "No trigger found in message.")
+            }
+        }
+        return Err(ReplyError::NoReplyFound);
+    }
+    let triggers: Vec<String> = selected.iter().map(|s| s.trigger.clone()).collect();
+    let delete_after = selected.iter().filter_map(|s| s.delete_after).min();
+    let steps = contents(settings.match_policy, &selected);
+    let channel_name = channel_name(ctx, new_message.channel_id).await?;
+
+    // Send the messages one after another. If other messages are sent in the channel in the
+    // meantime, the rest of the sequence would not make sense anymore.
+    let start = message_count(bot, new_message.channel_id);
+    let mut messages = Vec::new();
+    let mut sent = Vec::new();
+    let mut result = Ok(());
+    for (idx, step) in steps.iter().enumerate() {
+        let delay = step.delay()
+            + settings.human_delay.map_or(Duration::ZERO, |human_delay| {
+                human_delay.delay(step.content())
+            });
+        wait(ctx, new_message.channel_id, delay).await;
+        // The first message is always sent, only the rest of the sequence can be cancelled
+        let busy = message_count(bot, new_message.channel_id) - start;
+        if idx > 0 && busy >= u64::from(settings.cancel_sequences_after) {
+            debug!(
+                "{} messages have been sent in channel {} in the meantime. Cancelling the sequence.",
+                busy, new_message.channel_id
+            );
+            break;
+        }
+        match send(ctx, new_message, &bot, step.content(), &channel_name).await {
+            Ok(message) => {
+                cleanup::manage(
+                    ctx,
+                    &message,
+                    new_message.author.id,
+                    bot,
+                    delete_after,
+                    settings.delete_reaction,
+                )
+                .await;
+                messages.push(message.id);
+                sent.push(step.content());
+                remember_reply(bot, new_message.channel_id);
             }
             Err(why) => {
-                panic!(
-                    "Something went wrong internally: {:?}\nMutex is poisoned: {}",
-                    why, why
-                );
+                result = Err(why);
+                break;
             }
-        };
+        }
+    }
+
+    if !messages.is_empty() && (settings.reply_to_edits || settings.delete_orphaned_replies) {
+        HISTORY.lock().unwrap().insert(
+            bot,
+            new_message.id,
+            SentReplies {
+                author: new_message.author.id,
+                messages,
+                triggers,
+                conversion: None,
+            },
+        );
+    }
+    result.map(|_| sent.join("\n\n"))
+}
+
+/// Reacts to an edited message, if the bot is configured to do so.
+///
+/// If the bot has not replied to the message yet, it is handled like a new message
+/// (see [reply_to]). Otherwise the replies are edited to match the new triggers in the message,
+/// or deleted if there are no triggers left. If the triggers did not change, the replies stay
+/// the same.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `new`: The edited message, if it is in the cache.
+/// * `event`: The event that contains the changes to the message.
+/// * `bot`: The bot which reacts to the edit.
+///
+/// returns: Result<(), ReplyError>
+pub async fn reply_to_edit(
+    ctx: &Context,
+    new: Option<Message>,
+    event: &MessageUpdateEvent,
+    bot: Bots,
+) -> Result<(), ReplyError> {
+    let settings = settings(&bot);
+    // Edits that do not change the content, e.g. embeds being loaded, are ignored
+    if !settings.reply_to_edits || event.content.is_none() {
+        return Ok(());
+    }
+    let message = match new {
+        Some(message) => message,
+        None => event
+            .channel_id
+            .message(&ctx, event.id)
+            .await
+            .map_err(ReplyError::Fetching)?,
+    };
+    if message.author.bot || privacy::is_ignored(message.author.id, message.guild_id) {
+        return Ok(());
+    }
 
-        reply_vector
+    let previous = HISTORY.lock().unwrap().get(bot, message.id).cloned();
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            debug!(
+                "Message {} was edited. Searching it for triggers...",
+                message.id
+            );
+            return reply(ctx, &message, bot, settings).await.map(|_| ());
+        }
     };
-    trace!("Acquired replies.");
 
-    // Go through all the replies and then check if to reply to this message and with what
-    // Get the name in a separate scope to not copy rng into the async part of message sending
-    trace!("Checking for response with RegEx");
-    let response_option = {
-        let mut out: Option<String> = None;
+    // The chance has already been rolled, when the message was sent
+    let selected = select_responses(&settings, &message, false);
+    let triggers: Vec<String> = selected.iter().map(|s| s.trigger.clone()).collect();
+    if triggers == previous.triggers {
+        trace!("The triggers of message {} did not change", message.id);
+        return Ok(());
+    }
+    if selected.is_empty() {
+        debug!("Message {} does not contain triggers anymore", message.id);
+        return remove_replies(ctx, message.channel_id, message.id, bot).await;
+    }
 
-        for reply in replies {
-            // If out has been set, set the reply and continue.
-            if out.is_some() {
-                break;
+    // Edit the old replies, delete the ones that are not needed anymore and send the missing ones.
+    // The delays of sequences are ignored, as the edit has already happened.
+    let delete_after = selected.iter().filter_map(|s| s.delete_after).min();
+    let steps = contents(settings.match_policy, &selected);
+    let channel_name = channel_name(ctx, message.channel_id).await?;
+    let mut messages = Vec::new();
+    for (idx, step) in steps.iter().enumerate() {
+        let content = step.content();
+        match previous.messages.get(idx) {
+            Some(reply) => {
+                let edited = message
+                    .channel_id
+                    .edit_message(&ctx, *reply, EditMessage::new().content(content))
+                    .await
+                    .map_err(ReplyError::Editing)?;
+                info!(
+                    "Edited message {} to \"{}\" in channel #{} ({})",
+                    edited.id,
+                    edited.content.replace("\n", "\\n"),
+                    channel_name,
+                    edited.channel_id
+                );
+                messages.push(edited.id);
             }
-
-            // Check if current key is part of the current message
-            for trigger in &reply.trigger {
-                if new_message
-                    .content
-                    .to_lowercase()
-                    .contains(&trigger.as_str().unwrap().to_lowercase())
-                {
-                    debug!(
-                        "Found trigger {} in message (id: {})",
-                        trigger, new_message.id
-                    );
-                    // Select random answer from pool
-                    let mut rng = rand::thread_rng();
-                    trace!("Response pool is: {:#?}", reply.response_pool);
-                    let response_idx = rng.gen_range(0..reply.response_pool.len());
-                    trace!("Response index is: {}", response_idx);
-                    let response_value: &toml::Value =
-                        reply.response_pool.get(response_idx).unwrap();
-                    trace!("Response value is: {}", response_value.to_string());
-                    out = Some(String::from(response_value.as_str().unwrap()));
-
-                    break;
-                }
+            None => {
+                let reply = send(ctx, &message, &bot, content, &channel_name).await?;
+                cleanup::manage(
+                    ctx,
+                    &reply,
+                    message.author.id,
+                    bot,
+                    delete_after,
+                    settings.delete_reaction,
+                )
+                .await;
+                messages.push(reply.id);
             }
         }
-        out
-    }; // let response
+    }
+    for reply in previous.messages.iter().skip(steps.len()) {
+        delete_message(ctx, message.channel_id, *reply).await?;
+    }
 
-    // We can not unwrap response_option everywhere where response is used, because
-    // Option<String> does not implement the Copy trait, but String does. This is,
-    // why a String is needed from here on.
-    let response = match response_option {
-        None => {
-            match bot {
-                Bots::Autokommentator => {
-                    debug!(target: "xdbot::xd", "No trigger found in message.")
-                }
-                Bots::KaenguruKnecht => {
-                    debug!(target: "xdbot::kaenguru", "No trigger found in message.")
-                }
-            }
-            return Err(ReplyError::NoReplyFound);
+    HISTORY.lock().unwrap().insert(
+        bot,
+        message.id,
+        SentReplies {
+            author: message.author.id,
+            messages,
+            triggers,
+            conversion: None,
+        },
+    );
+    Ok(())
+}
+
+/// Deletes the replies of the bot to a message, if the bot is configured to do so.
+/// Should be called, when the message has been deleted.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `channel`: The channel in which the message was sent.
+/// * `message`: The message whose replies should be deleted.
+/// * `bot`: The bot which has sent the replies.
+///
+/// returns: Result<(), ReplyError>
+pub async fn delete_replies(
+    ctx: &Context,
+    channel: ChannelId,
+    message: MessageId,
+    bot: Bots,
+) -> Result<(), ReplyError> {
+    if !settings(&bot).delete_orphaned_replies {
+        return Ok(());
+    }
+    remove_replies(ctx, channel, message, bot).await
+}
+
+/// Deletes the replies of the bot to a message and forgets them.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `channel`: The channel in which the message was sent.
+/// * `message`: The message whose replies should be deleted.
+/// * `bot`: The bot which has sent the replies.
+///
+/// returns: Result<(), ReplyError>
+pub async fn remove_replies(
+    ctx: &Context,
+    channel: ChannelId,
+    message: MessageId,
+    bot: Bots,
+) -> Result<(), ReplyError> {
+    let previous = HISTORY.lock().unwrap().remove(bot, message);
+    if let Some(previous) = previous {
+        for reply in previous.messages {
+            delete_message(ctx, channel, reply).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Collects what the bots remember about the messages of a user, until they are restarted.
+///
+/// # Arguments
+///
+/// * `user`: The user to collect the data of
+///
+/// returns: serde_json::Value
+pub fn about(user: UserId) -> serde_json::Value {
+    let replied_messages = HISTORY
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|replies| replies.author == user)
+        .count();
+    serde_json::json!({
+        "replied_messages": replied_messages,
+        "deletable_replies": cleanup::deletable_by(user),
+    })
+}
+
+/// Forgets everything the bots remember about the messages of a user. Their replies are not
+/// edited or deleted with the messages anymore.
+pub fn forget(user: UserId) {
+    HISTORY
+        .lock()
+        .unwrap()
+        .retain(|replies| replies.author != user);
+    cleanup::forget(user);
+}
+
+/// Copies the settings of a bot out of the [CONFIG].
+fn settings(bot: &Bots) -> Settings {
+    trace!("Getting replies from configuration...");
+    let settings = match CONFIG.lock() {
+        // Only the pointer to the compiled triggers is copied
+        Ok(config) => match bot {
+            Bots::Autokommentator => Settings {
+                matcher: Arc::clone(&config.autokommentator.matcher),
+                match_policy: config.autokommentator.match_policy,
+                min_messages_between_replies: config.autokommentator.min_messages_between_replies,
+                timezone: config.autokommentator.timezone,
+                reply_to_edits: config.autokommentator.reply_to_edits,
+                delete_orphaned_replies: config.autokommentator.delete_orphaned_replies,
+                cancel_sequences_after: config.autokommentator.cancel_sequences_after,
+                human_delay: config.autokommentator.human_delay,
+                delete_reaction: config.autokommentator.delete_reaction,
+            },
+            Bots::KaenguruKnecht => Settings {
+                matcher: Arc::clone(&config.kaenguru.matcher),
+                match_policy: config.kaenguru.match_policy,
+                min_messages_between_replies: config.kaenguru.min_messages_between_replies,
+                timezone: config.kaenguru.timezone,
+                reply_to_edits: config.kaenguru.reply_to_edits,
+                delete_orphaned_replies: config.kaenguru.delete_orphaned_replies,
+                cancel_sequences_after: config.kaenguru.cancel_sequences_after,
+                human_delay: config.kaenguru.human_delay,
+                delete_reaction: config.kaenguru.delete_reaction,
+            },
+        },
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
         }
-        Some(s) => s,
     };
+    trace!("Acquired replies.");
+    settings
+}
+
+/// Searches a message for all responses that have a trigger in it, selects the responses to use
+/// with the match policy of the bot and selects an answer from each of them.
+///
+/// # Arguments
+///
+/// * `settings`: The settings of the bot
+/// * `message`: The message to search for triggers
+/// * `roll_chance`: If responses should be skipped by their chance
+///
+/// returns: Vec<Selected>: The trigger and the answer of every selected response
+fn select_responses(settings: &Settings, message: &Message, roll_chance: bool) -> Vec<Selected> {
+    trace!("Checking for response with the trigger index");
+    let mut rng = rand::thread_rng();
+    // Responses that are not scheduled for now are ignored
+    let now = local_time(settings.timezone);
+    trace!("Local time is: {}", now);
+    let active = |reply: &Response| {
+        reply
+            .active
+            .as_ref()
+            .is_none_or(|active| active.is_active(&now))
+    };
+    let policy = settings.match_policy;
+    let matches = match policy {
+        // Only the first response is needed, so no list of all matches is built
+        MatchPolicy::FirstMatch => settings
+            .matcher
+            .find_where(&message.content, active)
+            .into_iter()
+            .collect(),
+        _ => policy.select(
+            settings
+                .matcher
+                .find_all(&message.content)
+                .into_iter()
+                .filter(|m| active(m.item))
+                .collect(),
+        ),
+    };
+    trace!("Match policy is: {:?}", policy);
+
+    matches
+        .into_iter()
+        // Only reply with the configured probability
+        .filter(|m| {
+            let chance = m.item.chance.unwrap_or(1.0);
+            let hit = !roll_chance || rand::thread_rng().gen_bool(chance);
+            if !hit {
+                debug!(
+                    "Skipping trigger {} in message (id: {}) by chance ({})",
+                    m.trigger, message.id, chance
+                );
+            }
+            hit
+        })
+        .filter_map(
+            |Match {
+                 item: reply,
+                 trigger,
+                 distance,
+             }| {
+                debug!(
+                    "Found trigger {} with distance {} in message (id: {})",
+                    trigger, distance, message.id
+                );
+                // Select random answer from pool
+                trace!("Response pool is: {:#?}", reply.response_pool);
+                let response_idx = rng.gen_range(0..reply.response_pool.len());
+                trace!("Response index is: {}", response_idx);
+                let response_value: &toml::Value = reply.response_pool.get(response_idx).unwrap();
+                trace!("Response value is: {}", response_value.to_string());
+                // The response pool has been validated, when the config was loaded
+                let selected: Reply = response_value.clone().try_into().unwrap();
+                Some(Selected {
+                    trigger: trigger.to_string(),
+                    reply: markov::resolve(selected, message.guild_id)?,
+                    delete_after: reply.delete_after.map(Duration::from_secs_f64),
+                })
+            },
+        )
+        .collect()
+}
+
+/// Gets the messages to send for the selected responses.
+///
+/// [MatchPolicy::AllSeparate] sends every message of every response on its own. All other
+/// policies combine the first messages of the responses into one message. The remaining
+/// messages of sequences are sent after it.
+fn contents(policy: MatchPolicy, selected: &[Selected]) -> Vec<SequenceStep> {
+    let steps: Vec<Vec<SequenceStep>> = selected.iter().map(|s| s.reply.steps()).collect();
+    match policy {
+        MatchPolicy::AllSeparate => steps.into_iter().flatten().collect(),
+        _ => {
+            let first = SequenceStep::Delayed {
+                message: steps
+                    .iter()
+                    .map(|steps| steps[0].content())
+                    .collect::<Vec<&str>>()
+                    .join("\n\n"),
+                delay: steps[0][0].delay().as_secs_f64(),
+            };
+            std::iter::once(first)
+                .chain(steps.iter().flat_map(|steps| steps[1..].iter().cloned()))
+                .collect()
+        }
+    }
+}
 
+/// Gets the name of a channel, used for logging.
+async fn channel_name(ctx: &Context, channel: ChannelId) -> Result<String, ReplyError> {
     // Get the channel and only react to private messages and server-messages
-    let message_channel = new_message
-        .channel_id
+    let message_channel = channel
         .to_channel(&ctx)
         .await
-        .map_err(|why| ReplyError::ChannelError(why))?;
+        .map_err(ReplyError::ChannelError)?;
+    Ok(match message_channel {
+        Channel::Private(c) => format!("DM:{}", c.recipient.name),
+        Channel::Guild(c) => c.name,
+        _ => "Not a channel".to_string(),
+    })
+}
+
+/// Deletes a reply of the bot and logs the result.
+async fn delete_message(
+    ctx: &Context,
+    channel: ChannelId,
+    message: MessageId,
+) -> Result<(), ReplyError> {
+    match channel.delete_message(&ctx, message).await {
+        Ok(_) => {
+            info!("Deleted message {} in channel {}", message, channel);
+            Ok(())
+        }
+        Err(why) => {
+            error!("Error deleting message {}: {:?}", message, why);
+            Err(ReplyError::Deleting(why))
+        }
+    }
+}
+
+/// Gets the current time in the timezone of a bot, or the local timezone of the server.
+pub fn local_time(timezone: Option<Tz>) -> NaiveDateTime {
+    match timezone {
+        Some(timezone) => Utc::now().with_timezone(&timezone).naive_local(),
+        None => Local::now().naive_local(),
+    }
+}
+
+/// Counts a new message in a channel.
+fn count_message(bot: Bots, channel: ChannelId) {
+    ACTIVITY
+        .lock()
+        .unwrap()
+        .entry((bot, channel))
+        .or_default()
+        .messages += 1;
+}
 
-    match new_message.reply(&ctx, &response).await {
+/// Checks if enough messages have been sent in a channel since the last reply of the bot in it.
+///
+/// # Arguments
+///
+/// * `bot`: The bot which wants to reply.
+/// * `channel`: The channel in which the message was sent.
+/// * `min_messages`: The minimum number of messages between two replies of the bot.
+///
+/// returns: bool: If the bot may reply to the message.
+fn cooled_down(bot: Bots, channel: ChannelId, min_messages: u32) -> bool {
+    let activity = ACTIVITY.lock().unwrap();
+    let activity = match activity.get(&(bot, channel)) {
+        Some(activity) => activity,
+        None => return true,
+    };
+    // The message itself does not count as a message between two replies
+    activity
+        .last_reply
+        .is_none_or(|last_reply| activity.messages - last_reply > u64::from(min_messages))
+}
+
+/// Gets the number of messages the bot has seen in a channel.
+fn message_count(bot: Bots, channel: ChannelId) -> u64 {
+    ACTIVITY
+        .lock()
+        .unwrap()
+        .get(&(bot, channel))
+        .map_or(0, |activity| activity.messages)
+}
+
+/// Remembers that the bot has just replied in a channel.
+fn remember_reply(bot: Bots, channel: ChannelId) {
+    let mut activity = ACTIVITY.lock().unwrap();
+    let activity = activity.entry((bot, channel)).or_default();
+    activity.last_reply = Some(activity.messages);
+}
+
+/// Shows the typing indicator in a channel for a while, before the next message is sent.
+async fn wait(ctx: &Context, channel: ChannelId, delay: Duration) {
+    if delay.is_zero() {
+        return;
+    }
+    trace!("Waiting {:?} before sending the next message", delay);
+    let typing = channel.start_typing(&ctx.http);
+    tokio::time::sleep(delay).await;
+    typing.stop();
+}
+
+/// Sends a reply to a message and logs the result.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `new_message`: The message to reply to.
+/// * `bot`: The bot which sends the reply.
+/// * `response`: The content of the reply.
+/// * `channel_name`: The name of the channel, used for logging.
+///
+/// returns: Result<Message, ReplyError>
+async fn send(
+    ctx: &Context,
+    new_message: &Message,
+    bot: &Bots,
+    response: &str,
+    channel_name: &str,
+) -> Result<Message, ReplyError> {
+    match new_message.reply(&ctx, response).await {
         Ok(msg) => {
-            let channel_name = match message_channel {
-                Channel::Private(c) => format!("DM:{}", c.recipient.name),
-                Channel::Guild(c) => c.name,
-                _ => "Not a channel".to_string(),
-            };
             match bot {
                 Bots::Autokommentator => {
                     info!(target: "xd_bot::xd",
@@ -161,6 +717,7 @@ pub async fn reply_to(
                     );
                 }
             };
+            Ok(msg)
         }
         Err(why) => {
             match bot {
@@ -171,9 +728,7 @@ pub async fn reply_to(
                     error!(target: "xd_bot::kaenguru", "Error sending message: {:?}", why)
                 }
             }
-            return Err(ReplyError::Sending(why));
+            Err(ReplyError::Sending(why))
         }
-    };
-
-    Ok(response)
+    }
 }
@@ -0,0 +1,170 @@
+//!
+//! This module deletes replies of the bots again: Either automatically after a while
+//! (`delete_after`), or when the user who triggered the reply or a moderator reacts to it
+//! with [DELETE_REACTION].
+//!
+//! The deletions are scheduled on the tokio runtime and only use the HTTP API of discord, so they
+//! still happen, if the connection to the gateway is lost and reestablished in the meantime.
+//!
+
+use crate::config::Bots;
+use crate::replies::history::{History, CAPACITY};
+use log::{debug, error, info};
+use once_cell::sync::Lazy;
+use serenity::{
+    model::{
+        channel::{Message, Reaction, ReactionType},
+        id::{ChannelId, MessageId, UserId},
+    },
+    prelude::*,
+};
+use std::{sync::Mutex, time::Duration};
+
+/// The reaction with which a reply can be deleted
+pub const DELETE_REACTION: &str = "🗑️";
+
+/// The users who have triggered the replies, which can be deleted with a reaction
+static TRIGGERED_BY: Lazy<Mutex<History<UserId>>> =
+    Lazy::new(|| Mutex::new(History::new(CAPACITY)));
+
+/// Schedules the deletion of a reply and lets the user delete it with a reaction, if the bot is
+/// configured to do so.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `reply`: The reply of the bot.
+/// * `author`: The user who has sent the message the bot has replied to.
+/// * `bot`: The bot which has sent the reply.
+/// * `delete_after`: The time after which the reply should be deleted.
+/// * `reaction`: If the reply can be deleted with [DELETE_REACTION].
+///
+/// returns: ()
+pub async fn manage(
+    ctx: &Context,
+    reply: &Message,
+    author: UserId,
+    bot: Bots,
+    delete_after: Option<Duration>,
+    reaction: bool,
+) {
+    if let Some(delay) = delete_after {
+        schedule_delete(ctx, reply.channel_id, reply.id, delay);
+    }
+    if reaction {
+        TRIGGERED_BY.lock().unwrap().insert(bot, reply.id, author);
+        let emoji = ReactionType::Unicode(DELETE_REACTION.to_string());
+        if let Err(why) = reply.react(&ctx, emoji).await {
+            error!(
+                "Could not add the delete reaction to message {}: {:?}",
+                reply.id, why
+            );
+        }
+    }
+}
+
+/// Deletes a reply of the bot, if the user who triggered it or a moderator reacted to it
+/// with [DELETE_REACTION].
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `reaction`: The reaction that was added to a message.
+/// * `bot`: The bot which received the reaction.
+///
+/// returns: ()
+pub async fn delete_on_reaction(ctx: &Context, reaction: &Reaction, bot: Bots) {
+    if !reaction.emoji.unicode_eq(DELETE_REACTION) {
+        return;
+    }
+    let user = match reaction.user_id {
+        // The reaction of the bot itself does not count
+        Some(user) if user != ctx.cache.current_user().id => user,
+        _ => return,
+    };
+    let author = match TRIGGERED_BY.lock().unwrap().get(bot, reaction.message_id) {
+        Some(author) => *author,
+        None => return,
+    };
+
+    if user != author && !is_moderator(ctx, reaction) {
+        debug!(
+            "User {} is not allowed to delete message {}",
+            user, reaction.message_id
+        );
+        return;
+    }
+    match reaction
+        .channel_id
+        .delete_message(&ctx, reaction.message_id)
+        .await
+    {
+        Ok(_) => {
+            TRIGGERED_BY
+                .lock()
+                .unwrap()
+                .remove(bot, reaction.message_id);
+            info!(
+                "Deleted message {} on request of user {}",
+                reaction.message_id, user
+            );
+        }
+        Err(why) => error!("Error deleting message {}: {:?}", reaction.message_id, why),
+    }
+}
+
+/// Counts the replies which a user can delete with a reaction, because they have triggered them.
+pub fn deletable_by(user: UserId) -> usize {
+    TRIGGERED_BY
+        .lock()
+        .unwrap()
+        .values()
+        .filter(|author| **author == user)
+        .count()
+}
+
+/// Forgets which replies a user has triggered. Afterwards they cannot be deleted with a reaction
+/// anymore.
+pub fn forget(user: UserId) {
+    TRIGGERED_BY
+        .lock()
+        .unwrap()
+        .retain(|author| *author != user);
+}
+
+/// Deletes a message after a delay, without blocking the current task.
+fn schedule_delete(ctx: &Context, channel: ChannelId, message: MessageId, delay: Duration) {
+    debug!("Deleting message {} in {:?}", message, delay);
+    let http = ctx.http.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        match channel.delete_message(&http, message).await {
+            Ok(_) => info!("Deleted expired message {} in channel {}", message, channel),
+            Err(why) => debug!("Could not delete expired message {}: {:?}", message, why),
+        }
+    });
+}
+
+/// Checks if the user who added a reaction can manage messages in the channel. In threads, the
+/// permissions of the parent channel are checked.
+fn is_moderator(ctx: &Context, reaction: &Reaction) -> bool {
+    let (guild, member) = match (reaction.guild_id, &reaction.member) {
+        (Some(guild), Some(member)) => (guild, member),
+        _ => return false,
+    };
+    let guild = match guild.to_guild_cached(&ctx) {
+        Some(guild) => guild,
+        None => return false,
+    };
+    // Threads are not in the channels of the guild, but their parent channels are
+    let channel_id = guild
+        .threads
+        .iter()
+        .find(|thread| thread.id == reaction.channel_id)
+        .and_then(|thread| thread.parent_id)
+        .unwrap_or(reaction.channel_id);
+    match guild.channels.get(&channel_id) {
+        Some(channel) => guild.user_permissions_in(channel, member).manage_messages(),
+        None => false,
+    }
+}
@@ -0,0 +1,116 @@
+//!
+//! This module remembers which replies the bots have sent to which messages, so that the replies
+//! can be edited or deleted, when the message they reply to is edited or deleted.
+//!
+//! Only the last [CAPACITY] messages are remembered. Older ones are forgotten, so that the memory
+//! usage of the bots does not grow forever.
+//!
+
+use crate::config::Bots;
+use once_cell::sync::Lazy;
+use serenity::model::id::{MessageId, UserId};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// The number of messages, whose replies are remembered
+pub const CAPACITY: usize = 1000;
+
+/// The replies of all bots, which are still remembered
+pub static HISTORY: Lazy<Mutex<History<SentReplies>>> =
+    Lazy::new(|| Mutex::new(History::new(CAPACITY)));
+
+/// The replies a bot has sent to a single message
+#[derive(Clone, Debug)]
+pub struct SentReplies {
+    /// The user who has sent the message
+    pub author: UserId,
+    /// The replies, in the order they were sent
+    pub messages: Vec<MessageId>,
+    /// The triggers that were found in the message
+    pub triggers: Vec<String>,
+    /// The text of the embed with the converted amounts, if the Känguru Knecht has sent one
+    /// instead of replies
+    pub conversion: Option<String>,
+}
+
+/// A map from the messages of a bot to some data about them (e.g. the replies to them), which
+/// forgets the oldest messages when it is full
+pub struct History<V> {
+    /// The maximum number of messages to remember
+    capacity: usize,
+    /// The remembered messages, from the oldest to the newest
+    order: VecDeque<(Bots, MessageId)>,
+    /// The data of each remembered message
+    replies: HashMap<(Bots, MessageId), V>,
+}
+
+impl<V> History<V> {
+    /// Creates a new, empty history.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity`: The maximum number of messages to remember
+    ///
+    /// returns: History<V>
+    pub fn new(capacity: usize) -> History<V> {
+        History {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            replies: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// Remembers the data of a message, e.g. the replies of a bot to it. If the history is full,
+    /// the oldest message is forgotten.
+    ///
+    /// # Arguments
+    ///
+    /// * `bot`: The bot to which the data belongs
+    /// * `message`: The message to remember
+    /// * `replies`: The data of the message
+    ///
+    /// returns: ()
+    pub fn insert(&mut self, bot: Bots, message: MessageId, replies: V) {
+        if self.replies.insert((bot, message), replies).is_some() {
+            return;
+        }
+        self.order.push_back((bot, message));
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.replies.remove(&oldest);
+            }
+        }
+    }
+
+    /// Gets the data of a message, if it is still remembered.
+    pub fn get(&self, bot: Bots, message: MessageId) -> Option<&V> {
+        self.replies.get(&(bot, message))
+    }
+
+    /// Forgets a message and returns its data.
+    pub fn remove(&mut self, bot: Bots, message: MessageId) -> Option<V> {
+        let replies = self.replies.remove(&(bot, message))?;
+        self.order.retain(|key| *key != (bot, message));
+        Some(replies)
+    }
+
+    /// Gets the data of all remembered messages.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.replies.values()
+    }
+
+    /// Forgets all messages whose data does not match a condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep`: Checks if a message should be remembered further
+    ///
+    /// returns: ()
+    pub fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) {
+        self.replies.retain(|_, replies| keep(replies));
+        let replies = &self.replies;
+        self.order.retain(|key| replies.contains_key(key));
+    }
+}
@@ -0,0 +1,337 @@
+//!
+//! This module posts the messages, that the bots send on their own at scheduled times
+//! (see [ScheduledPost]).
+//!
+//! Every scheduled post runs in its own task on the tokio runtime. The time of the last post is
+//! stored, so a post that was missed while the program was not running can be made up after a
+//! restart, if `catch_up` is set.
+//!
+//! * `/geplant`: Lists the upcoming posts of a bot in the current server. Only users who can
+//!   manage the server can use it.
+//!
+
+use crate::config::{scheduled::ScheduledPost, Bots, Reply, SequenceStep, CONFIG};
+use crate::markov;
+use crate::matcher::Searchable;
+use crate::storage::{self, DATA};
+use chrono::{DateTime, Local, Utc};
+use chrono_tz::Tz;
+use log::{debug, error, info};
+use rand::seq::SliceRandom;
+use serenity::{
+    builder::{CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage},
+    http::Http,
+    model::{application::CommandInteraction, id::ChannelId, Permissions},
+    prelude::*,
+};
+use std::sync::Arc;
+
+/// The maximum number of posts that are listed by `/geplant`, so the answer fits into a message
+const MAX_LISTED: usize = 20;
+
+/// Starts the tasks that send the scheduled posts of a bot.
+///
+/// # Arguments
+///
+/// * `bot`: The bot whose posts are sent
+/// * `http`: The HTTP client of the bot, with which the posts are sent
+///
+/// returns: ()
+pub fn start(bot: Bots, http: Arc<Http>) {
+    let (posts, timezone) = posts(bot);
+    if !posts.is_empty() {
+        info!("Scheduling {} posts of the {:?}", posts.len(), bot);
+    }
+    for post in posts {
+        tokio::spawn(run(bot, http.clone(), post, timezone));
+    }
+}
+
+/// Gets the scheduled posts and the timezone of a bot.
+fn posts(bot: Bots) -> (Vec<ScheduledPost>, Option<Tz>) {
+    match CONFIG.lock() {
+        Ok(config) => match bot {
+            Bots::Autokommentator => (
+                config.autokommentator.scheduled.clone(),
+                config.autokommentator.timezone,
+            ),
+            Bots::KaenguruKnecht => (config.kaenguru.scheduled.clone(), config.kaenguru.timezone),
+        },
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
+        }
+    }
+}
+
+/// The key under which the time of the last post is stored. Posts at the same time in the same
+/// channels are told apart by their message or response.
+fn key(bot: Bots, post: &ScheduledPost) -> String {
+    format!(
+        "{:?} {} {:?} {:?} {:?}",
+        bot, post.cron.0, post.channels, post.message, post.response
+    )
+}
+
+/// Gets the next time a post is due after a given time.
+///
+/// # Arguments
+///
+/// * `post`: The scheduled post
+/// * `after`: The time after which the post is due
+/// * `timezone`: The timezone of the bot, or None for the local timezone of the server
+///
+/// returns: Option<DateTime<Utc>>: None, if the post is never due again.
+fn next_after(
+    post: &ScheduledPost,
+    after: DateTime<Utc>,
+    timezone: Option<Tz>,
+) -> Option<DateTime<Utc>> {
+    match timezone {
+        Some(timezone) => post
+            .cron
+            .0
+            .after(&after.with_timezone(&timezone))
+            .next()
+            .map(|time| time.with_timezone(&Utc)),
+        None => post
+            .cron
+            .0
+            .after(&after.with_timezone(&Local))
+            .next()
+            .map(|time| time.with_timezone(&Utc)),
+    }
+}
+
+/// Sends a scheduled post every time it is due.
+async fn run(bot: Bots, http: Arc<Http>, post: ScheduledPost, timezone: Option<Tz>) {
+    let key = key(bot, &post);
+    let stored = DATA.lock().unwrap().scheduled_runs.get(&key).copied();
+    let mut last = match stored {
+        Some(last) => last,
+        None => {
+            // Nothing was missed before the post was configured
+            let now = Utc::now();
+            let _ = storage::update(|data| data.scheduled_runs.insert(key.clone(), now));
+            now
+        }
+    };
+
+    while let Some(next) = next_after(&post, last, timezone) {
+        let now = Utc::now();
+        let missed = next <= now;
+        if !missed {
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+        }
+        if missed && !post.catch_up {
+            debug!("Skipping the missed post {} of the {:?}", post.cron.0, bot);
+        } else {
+            send(bot, &http, &post).await;
+        }
+        // All other missed posts are skipped
+        last = match missed {
+            true => now,
+            false => next,
+        };
+        let _ = storage::update(|data| data.scheduled_runs.insert(key.clone(), last));
+    }
+    debug!(
+        "The post {} of the {:?} is never due again",
+        post.cron.0, bot
+    );
+}
+
+/// Sends a scheduled post to all of its channels.
+async fn send(bot: Bots, http: &Arc<Http>, post: &ScheduledPost) {
+    let steps = match (&post.message, &post.response) {
+        (Some(message), _) => vec![SequenceStep::Message(message.clone())],
+        (None, Some(trigger)) => match random_reply(bot, trigger) {
+            // Generated replies need a guild, which a scheduled post does not have
+            Some(reply) => match markov::resolve(reply, None) {
+                Some(reply) => reply.steps(),
+                None => {
+                    error!(
+                        "The response {} of the {:?} can not be generated for a scheduled post",
+                        trigger, bot
+                    );
+                    return;
+                }
+            },
+            None => {
+                error!("The {:?} has no response with the trigger {}", bot, trigger);
+                return;
+            }
+        },
+        (None, None) => return,
+    };
+    for channel in &post.channels {
+        let channel = ChannelId::new(*channel);
+        for step in &steps {
+            tokio::time::sleep(step.delay()).await;
+            match channel.say(http, step.content()).await {
+                Ok(_) => info!(
+                    "Sent scheduled message \"{}\" to {}",
+                    step.content().replace("\n", "\\n"),
+                    channel
+                ),
+                Err(why) => error!("Error sending scheduled message to {}: {:?}", channel, why),
+            }
+        }
+    }
+}
+
+/// Chooses a random reply from the response pool of the response with a trigger.
+fn random_reply(bot: Bots, trigger: &str) -> Option<Reply> {
+    let reply = match CONFIG.lock() {
+        Ok(config) => {
+            let responses = match bot {
+                Bots::Autokommentator => &config.autokommentator.responses,
+                Bots::KaenguruKnecht => &config.kaenguru.responses,
+            };
+            responses
+                .iter()
+                .find(|response| response.triggers().iter().any(|found| found == trigger))?
+                .response_pool
+                .choose(&mut rand::thread_rng())?
+                .clone()
+        }
+        Err(why) => {
+            panic!(
+                "Something went wrong internally: {:?}\nMutex is poisoned: {}",
+                why, why
+            );
+        }
+    };
+    // The response pool has been validated, when the config was loaded
+    reply.try_into().ok()
+}
+
+/// The slash commands of this module, which have to be registered with discord
+pub fn commands() -> Vec<CreateCommand> {
+    vec![CreateCommand::new("geplant")
+        .description("Zeigt die nächsten geplanten Nachrichten")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .dm_permission(false)]
+}
+
+/// Runs a slash command of this module.
+///
+/// # Arguments
+///
+/// * `ctx`: The [context](serenity::client::context) in which the bot operates.
+/// * `command`: The slash command a user has used.
+/// * `bot`: The bot which received the command.
+///
+/// returns: bool: If the command belongs to this module.
+pub async fn run_command(ctx: &Context, command: &CommandInteraction, bot: Bots) -> bool {
+    if command.data.name != "geplant" {
+        return false;
+    }
+    // Only the posts in the channels of the guild, in which the command was used, are listed
+    let guild_channels: Vec<ChannelId> = match command.guild_id {
+        Some(guild) => match guild.channels(&ctx.http).await {
+            Ok(channels) => channels.into_keys().collect(),
+            Err(why) => {
+                error!("Could not get the channels of guild {}: {:?}", guild, why);
+                Vec::new()
+            }
+        },
+        None => Vec::new(),
+    };
+    let (posts, timezone) = posts(bot);
+    let posts: Vec<ScheduledPost> = posts
+        .into_iter()
+        .filter_map(|mut post| {
+            post.channels
+                .retain(|channel| guild_channels.contains(&ChannelId::new(*channel)));
+            (!post.channels.is_empty()).then_some(post)
+        })
+        .collect();
+    let now = Utc::now();
+    let mut upcoming: Vec<(DateTime<Utc>, &ScheduledPost)> = posts
+        .iter()
+        .filter_map(|post| Some((next_after(post, now, timezone)?, post)))
+        .collect();
+    upcoming.sort_by_key(|(time, _)| *time);
+
+    let answer = match upcoming.is_empty() {
+        true => "Es sind keine Nachrichten geplant.".to_string(),
+        false => upcoming
+            .iter()
+            .take(MAX_LISTED)
+            .map(|(time, post)| {
+                let channels: Vec<String> = post
+                    .channels
+                    .iter()
+                    .map(|channel| format!("<#{}>", channel))
+                    .collect();
+                let content = match (&post.message, &post.response) {
+                    (Some(message), _) => message.clone(),
+                    (None, Some(trigger)) => format!("Antwort auf \"{}\"", trigger),
+                    (None, None) => String::new(),
+                };
+                // Discord shows the time in the timezone of the user
+                format!(
+                    "<t:{}:F> in {}: {}",
+                    time.timestamp(),
+                    channels.join(", "),
+                    content
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n"),
+    };
+
+    let response = CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(answer)
+            .ephemeral(true),
+    );
+    if let Err(why) = command.create_response(&ctx.http, response).await {
+        error!(
+            "Could not answer the command {}: {:?}",
+            command.data.name, why
+        );
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::scheduled::CronSchedule;
+
+    /// Creates a post every monday at 9:00 in a channel
+    fn post(message: Option<&str>, response: Option<&str>) -> ScheduledPost {
+        ScheduledPost {
+            cron: CronSchedule::try_from("0 9 * * Mon".to_string()).unwrap(),
+            channels: vec![123],
+            message: message.map(String::from),
+            response: response.map(String::from),
+            catch_up: false,
+        }
+    }
+
+    #[test]
+    fn posts_at_the_same_time_have_different_keys() {
+        let posts = [
+            post(Some("Guten Morgen!"), None),
+            post(Some("Moin!"), None),
+            post(None, Some("Guten Morgen!")),
+        ];
+        for (idx, first) in posts.iter().enumerate() {
+            for second in &posts[idx + 1..] {
+                assert_ne!(
+                    key(Bots::Autokommentator, first),
+                    key(Bots::Autokommentator, second)
+                );
+            }
+            assert_ne!(
+                key(Bots::Autokommentator, first),
+                key(Bots::KaenguruKnecht, first)
+            );
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//!
+//! This module stores the data the bots collect while they are running, e.g. which users do not
+//! want to be replied to.
+//!
+//! The data is kept in memory and written to a JSON file after every change, so it survives
+//! restarts of the program. The location of the file is read from the environment `DATA_FILE`,
+//! or if it was not set, defaults to `data.json`.
+//!
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{debug, error};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::UserId;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::Path,
+    sync::Mutex,
+};
+
+///
+/// The global, thread safe storage of all bots.
+///
+/// Use [update] to change it, so that the changes are written to the data file.
+///
+pub static DATA: Lazy<Mutex<Data>> = Lazy::new(|| Mutex::new(Data::load()));
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+/// All data that is stored persistently
+pub struct Data {
+    /// The users who do not want to be replied to by the bots
+    #[serde(default)]
+    pub opted_out: BTreeSet<UserId>,
+    /// When the quotes were posted the last time (as unix timestamp), so they are not repeated
+    #[serde(default)]
+    pub posted_quotes: BTreeMap<String, i64>,
+    /// The day on which the last "Zitat des Tages" was posted
+    #[serde(default)]
+    pub last_daily_quote: Option<NaiveDate>,
+    /// When the scheduled posts of the bots were sent the last time, so missed posts can be
+    /// made up after a restart
+    #[serde(default)]
+    pub scheduled_runs: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl Data {
+    /// Loads the data from the data file, or starts with empty data if it does not exist yet or
+    /// cannot be read.
+    fn load() -> Data {
+        let data_file = data_file_location();
+        if !Path::new(&data_file).exists() {
+            debug!(
+                "Data file {} does not exist yet. Starting empty.",
+                data_file
+            );
+            return Data::default();
+        }
+        // Without the data, the bots can still run. The invalid file is kept, so it can be repaired.
+        match fs::read_to_string(&data_file).map(|content| serde_json::from_str(&content)) {
+            Ok(Ok(data)) => data,
+            Ok(Err(why)) => {
+                let backup_file = format!("{}.invalid", data_file);
+                match fs::rename(&data_file, &backup_file) {
+                    Ok(_) => error!(
+                        "The data file {} is invalid: {}. Moved it to {} and starting empty.",
+                        data_file, why, backup_file
+                    ),
+                    Err(rename_error) => error!(
+                        "The data file {} is invalid: {}. Could not move it to {}: {}. Starting empty.",
+                        data_file, why, backup_file, rename_error
+                    ),
+                }
+                Data::default()
+            }
+            Err(why) => {
+                error!(
+                    "Could not read the data file {}: {}. Starting empty.",
+                    data_file, why
+                );
+                Data::default()
+            }
+        }
+    }
+
+    /// Writes the data to the data file (see [write_file]).
+    fn save(&self) -> Result<(), String> {
+        let data_file = data_file_location();
+        let content = serde_json::to_string_pretty(self).map_err(|why| why.to_string())?;
+        write_file(&data_file, &content)
+            .map_err(|why| format!("Could not write the data file {}: {}", data_file, why))
+    }
+
+    /// Collects everything that is stored about a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `user`: The user to collect the data of
+    ///
+    /// returns: serde_json::Value
+    pub fn about(&self, user: UserId) -> serde_json::Value {
+        serde_json::json!({
+            "user": user,
+            "opted_out": self.opted_out.contains(&user),
+        })
+    }
+
+    /// Deletes everything that is stored about a user.
+    pub fn forget(&mut self, user: UserId) {
+        self.opted_out.remove(&user);
+    }
+}
+
+/// Changes the stored data and writes it to the data file. The change is only kept, if the data
+/// file could be written.
+///
+/// # Arguments
+///
+/// * `change`: The function that changes the data
+///
+/// returns: Result<R, String>: The result of `change`, or why the data could not be saved.
+///
+/// # Examples
+///
+/// ```
+/// update(|data| data.opted_out.insert(user))?;
+/// ```
+pub fn update<R>(change: impl FnOnce(&mut Data) -> R) -> Result<R, String> {
+    let mut data = DATA.lock().unwrap();
+    let mut changed = data.clone();
+    let result = change(&mut changed);
+    if let Err(why) = changed.save() {
+        error!("{}", why);
+        return Err(why);
+    }
+    *data = changed;
+    Ok(result)
+}
+
+/// Writes a file at once: The content is written to a temporary file first, which then replaces
+/// the file. So the file is never only written half, if the program is stopped.
+///
+/// # Arguments
+///
+/// * `file`: The location of the file
+/// * `content`: The new content of the file
+///
+/// returns: Result<(), std::io::Error>
+pub fn write_file(file: &str, content: &str) -> std::io::Result<()> {
+    let temp_file = format!("{}.tmp", file);
+    fs::write(&temp_file, content).and_then(|_| fs::rename(&temp_file, file))
+}
+
+/// Gets the location of the data file.
+///
+/// returns: String
+fn data_file_location() -> String {
+    match env::var("DATA_FILE") {
+        Ok(o) => o,
+        Err(_) => {
+            debug!("DATA_FILE is not set. Using default \"data.json\"...");
+            "data.json".to_string()
+        }
+    }
+}
@@ -1,9 +1,18 @@
 use crate::config::Bots;
-use crate::replies::reply_to;
-use log::info;
+use crate::markov;
+use crate::privacy;
+use crate::replies::{cleanup, delete_replies, reply_to, reply_to_edit, ReplyError};
+use crate::scheduler;
+use log::{debug, error, info};
 use serenity::{
     async_trait,
-    model::{channel::Message, gateway::Ready},
+    model::{
+        application::{Command, Interaction},
+        channel::{Message, Reaction},
+        event::MessageUpdateEvent,
+        gateway::Ready,
+        id::{ChannelId, GuildId, MessageId},
+    },
     prelude::*,
 };
 
@@ -24,8 +33,114 @@ impl EventHandler for XDHandler {
             return;
         }
 
-        if let Ok(_) = reply_to(&ctx, &new_message, Bots::Autokommentator).await {
-            return;
+        match reply_to(&ctx, &new_message, Bots::Autokommentator).await {
+            Ok(_) | Err(ReplyError::NoReplyFound) => {}
+            Err(why) => debug!("Could not reply to message {}: {}", new_message.id, why),
+        }
+        // Learned after replying, so the message is not repeated right away
+        markov::learn(&new_message);
+    }
+
+    /// The method that reacts to edited messages.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which this message was edited.
+    /// * `new`: The edited message, if it was in the cache.
+    /// * `event`: The changes that were made to the message.
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        match reply_to_edit(&ctx, new, &event, Bots::Autokommentator).await {
+            Ok(_) | Err(ReplyError::NoReplyFound) => {}
+            Err(why) => debug!(
+                "Could not react to the edit of message {}: {}",
+                event.id, why
+            ),
+        }
+    }
+
+    /// The method that reacts to deleted messages.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which this message was deleted.
+    /// * `channel_id`: The channel in which the message was deleted.
+    /// * `deleted_message_id`: The message that was deleted.
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        if let Err(why) =
+            delete_replies(&ctx, channel_id, deleted_message_id, Bots::Autokommentator).await
+        {
+            debug!(
+                "Could not delete the replies to message {}: {}",
+                deleted_message_id, why
+            );
+        }
+    }
+
+    /// The method that reacts to multiple messages being deleted at once.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which the messages were deleted.
+    /// * `channel_id`: The channel in which the messages were deleted.
+    /// * `multiple_deleted_messages_ids`: The messages that were deleted.
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        _guild_id: Option<GuildId>,
+    ) {
+        for message in multiple_deleted_messages_ids {
+            if let Err(why) = delete_replies(&ctx, channel_id, message, Bots::Autokommentator).await
+            {
+                debug!(
+                    "Could not delete the replies to message {}: {}",
+                    message, why
+                );
+            }
+        }
+    }
+
+    /// The method that reacts to new reactions on messages.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which the reaction was added.
+    /// * `add_reaction`: The reaction that was added.
+    async fn reaction_add(&self, ctx: Context, add_reaction: Reaction) {
+        cleanup::delete_on_reaction(&ctx, &add_reaction, Bots::Autokommentator).await;
+    }
+
+    /// The method that reacts to slash commands.
+    /// This method is called by serenity.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`: The context in which the command was used.
+    /// * `interaction`: The interaction with the command.
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Command(command) = interaction {
+            if !privacy::run(&ctx, &command).await
+                && !scheduler::run_command(&ctx, &command, Bots::Autokommentator).await
+            {
+                debug!("Unknown command: {}", command.data.name);
+            }
         }
     }
 
@@ -37,9 +152,18 @@ impl EventHandler for XDHandler {
     /// * `_data_about_bot`: Some normal data about the newly created instance
     ///
     /// returns: ()
-    async fn ready(&self, _ctx: Context, data_about_bot: Ready) {
+    async fn ready(&self, ctx: Context, data_about_bot: Ready) {
         info!("Logged in as {}", data_about_bot.user.name);
 
+        let commands = privacy::commands()
+            .into_iter()
+            .chain(scheduler::commands())
+            .collect();
+        match Command::set_global_commands(&ctx.http, commands).await {
+            Ok(commands) => debug!("Registered {} slash commands", commands.len()),
+            Err(why) => error!("Could not register the slash commands: {:?}", why),
+        }
+
         //TODO create invite links
         //        let permissions = Permissions::default();
         //        match data_about_bot.user.invite_url(&ctx, permissions).await {